@@ -0,0 +1,116 @@
+//! Debug overlay text updates over many frames: the retained text sections the overlay
+//! uses now, against the old path that inserted a fresh `TextBundle` every frame.
+//!
+//! This is a proxy and lays nothing out: it runs on `MinimalPlugins`, without the UI or text
+//! plugins. It times the update systems and counts the `Text` components marked changed. The
+//! UI measures and lays out every changed `Text` again, so that count stands in for the
+//! layout work that retaining the text avoids.
+//!
+//! Run with `cargo bench --bench debug_overlay`.
+
+use std::time::Instant;
+
+use bevy::prelude::*;
+
+#[path = "../src/utils/ui.rs"]
+mod ui;
+
+use ui::set_section_text;
+
+const FRAMES: u32 = 10_000;
+const PANELS: usize = 8;
+
+#[derive(Component)]
+struct Panel {
+    index: usize,
+}
+
+/// Frame counter standing in for the game state the panels show.
+#[derive(Resource, Default)]
+struct Frame(u32);
+
+/// Text components marked changed, each of which would be laid out again by the UI.
+#[derive(Resource, Default)]
+struct TextChanges(usize);
+
+/// Panel text that, like most of the overlay, only changes now and then.
+fn panel_value(index: usize, frame: u32) -> String {
+    format!("Panel {}: {}", index, frame / (60 * (index as u32 + 1)))
+}
+
+fn spawn_panels(
+    mut commands: Commands,
+) {
+    for index in 0..PANELS {
+        commands.spawn((
+            Panel {
+                index,
+            },
+            TextBundle::from_section("", TextStyle::default()),
+        ));
+    }
+}
+
+fn advance_frame(
+    mut frame: ResMut<Frame>,
+) {
+    frame.0 += 1;
+}
+
+fn update_retained(
+    frame: Res<Frame>,
+    mut q_panels: Query<(&Panel, &mut Text)>,
+) {
+    for (panel, mut text) in q_panels.iter_mut() {
+        set_section_text(&mut text, 0, panel_value(panel.index, frame.0));
+    }
+}
+
+fn update_inserted(
+    mut commands: Commands,
+    frame: Res<Frame>,
+    q_panels: Query<(Entity, &Panel)>,
+) {
+    for (entity, panel) in q_panels.iter() {
+        commands.entity(entity).insert(TextBundle::from_section(
+            panel_value(panel.index, frame.0),
+            TextStyle::default(),
+        ));
+    }
+}
+
+fn count_text_changes(
+    mut text_changes: ResMut<TextChanges>,
+    q_changed_text: Query<(), Changed<Text>>,
+) {
+    text_changes.0 += q_changed_text.iter().count();
+}
+
+fn run<M>(label: &str, update_panels: impl IntoSystemConfigs<M>) {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .init_resource::<Frame>()
+        .init_resource::<TextChanges>()
+        .add_systems(Startup, spawn_panels)
+        .add_systems(Update, (advance_frame, update_panels, count_text_changes).chain());
+    // First update runs the startup systems and is left out of the measurement
+    app.update();
+    app.world_mut().resource_mut::<TextChanges>().0 = 0;
+
+    let start = Instant::now();
+    for _frame in 0..FRAMES {
+        app.update();
+    }
+    let elapsed = start.elapsed();
+    let text_changes = app.world().resource::<TextChanges>().0;
+    println!("{:<10} {:>8.2} us/frame {:>8} text changes in {} frames",
+        label,
+        elapsed.as_secs_f64() * 1e6 / FRAMES as f64,
+        text_changes,
+        FRAMES);
+}
+
+fn main() {
+    run("retained", update_retained);
+    run("inserted", update_inserted);
+}
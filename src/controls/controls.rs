@@ -1,8 +1,6 @@
-use std::process::exit;
-
 use bevy::{prelude::*, window::{CursorGrabMode, PrimaryWindow, WindowMode}};
 
-use crate::{entities::player::player::{Player, PlayerBailEvent}, utils::debug::DebugDisplay};
+use crate::{entities::player::player::{Player, PlayerBailEvent}, states::game_state::GameState, utils::debug::DebugDisplay};
 
 pub struct InputMap {
     pub left: KeyCode,
@@ -10,13 +8,39 @@ pub struct InputMap {
     pub back: KeyCode,
     pub forward: KeyCode,
     pub jump: KeyCode,
+    pub respawn: KeyCode,
+    pub trick_flip: KeyCode,
+    pub trick_grab: KeyCode,
     pub turn_r: KeyCode,
     pub turn_l: KeyCode,
-    pub close: KeyCode,
+    pub pause: KeyCode,
     pub fullscreen: KeyCode,
     pub debug_bail: KeyCode,
     pub debug_menu: KeyCode,
-    pub debug_reset_position: KeyCode,
+    pub debug_noclip: KeyCode,
+    pub debug_player_gizmos: KeyCode,
+    pub debug_time_pause: KeyCode,
+    pub debug_time_step: KeyCode,
+    pub debug_time_slower: KeyCode,
+    pub debug_time_faster: KeyCode,
+    pub console: KeyCode,
+    pub editor_toggle: KeyCode,
+    pub editor_save: KeyCode,
+    pub editor_up: KeyCode,
+    pub editor_down: KeyCode,
+    pub editor_manipulate: KeyCode,
+    pub editor_translate: KeyCode,
+    pub editor_rotate: KeyCode,
+    pub editor_scale: KeyCode,
+    pub editor_axis_x: KeyCode,
+    pub editor_axis_y: KeyCode,
+    pub editor_axis_z: KeyCode,
+    pub editor_palette_next: KeyCode,
+    pub editor_palette_previous: KeyCode,
+    pub editor_spawn: KeyCode,
+    pub editor_delete: KeyCode,
+    pub editor_cycle_body: KeyCode,
+    pub editor_cycle_layer: KeyCode,
 }
 
 impl Default for InputMap {
@@ -27,15 +51,43 @@ impl Default for InputMap {
             back: KeyCode::KeyS,
             forward: KeyCode::KeyW,
             jump: KeyCode::Space,
+            respawn: KeyCode::KeyR,
+            trick_flip: KeyCode::KeyJ,
+            trick_grab: KeyCode::KeyK,
             turn_r: KeyCode::ArrowRight,
             turn_l: KeyCode::ArrowLeft,
-            close: KeyCode::Escape,
+            pause: KeyCode::Escape,
             fullscreen: KeyCode::F11,
 
             // debug keys
             debug_bail: KeyCode::KeyB,
             debug_menu: KeyCode::F3,
-            debug_reset_position: KeyCode::KeyR,
+            debug_noclip: KeyCode::KeyV,
+            debug_player_gizmos: KeyCode::KeyG,
+            debug_time_pause: KeyCode::KeyP,
+            debug_time_step: KeyCode::Period,
+            debug_time_slower: KeyCode::Minus,
+            debug_time_faster: KeyCode::Equal,
+            console: KeyCode::Backquote,
+
+            // editor keys
+            editor_toggle: KeyCode::F4,
+            editor_save: KeyCode::F5,
+            editor_up: KeyCode::Space,
+            editor_down: KeyCode::ShiftLeft,
+            editor_manipulate: KeyCode::ControlLeft,
+            editor_translate: KeyCode::Digit1,
+            editor_rotate: KeyCode::Digit2,
+            editor_scale: KeyCode::Digit3,
+            editor_axis_x: KeyCode::KeyX,
+            editor_axis_y: KeyCode::KeyY,
+            editor_axis_z: KeyCode::KeyZ,
+            editor_palette_next: KeyCode::BracketRight,
+            editor_palette_previous: KeyCode::BracketLeft,
+            editor_spawn: KeyCode::KeyN,
+            editor_delete: KeyCode::Delete,
+            editor_cycle_body: KeyCode::KeyB,
+            editor_cycle_layer: KeyCode::KeyL,
         }
     }
 }
@@ -43,14 +95,23 @@ impl Default for InputMap {
 pub fn handle_key_window_functions(
     mut q_windows: Query<&mut Window, With<PrimaryWindow>>,
     key: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
 ) {
-    let mut primary_window = q_windows.single_mut();
     let input_map = InputMap::default();
 
-    if key.just_pressed(input_map.close) {
-        exit(0);
+    if key.just_pressed(input_map.pause) {
+        match state.get() {
+            GameState::Playing => next_state.set(GameState::Paused),
+            GameState::Paused => next_state.set(GameState::Playing),
+            _ => {}
+        }
     }
 
+    // There is no window to resize when running headless
+    let Ok(mut primary_window) = q_windows.get_single_mut() else {
+        return;
+    };
     if key.just_pressed(input_map.fullscreen) {
         match primary_window.mode {
             WindowMode::Windowed => {
@@ -67,41 +128,24 @@ pub fn handle_key_window_functions(
 }
 
 pub fn handle_debug_keys(
-    mut commands: Commands,
     key: Res<ButtonInput<KeyCode>>,
     mut ev_player_bail: EventWriter<PlayerBailEvent>,
-    mut q_debug_menu: Query<(Entity, &mut DebugDisplay)>,
-    mut q_player: Query<(Entity, &mut Player)>,
+    mut q_debug_menu: Query<&mut Visibility, With<DebugDisplay>>,
+    q_player: Query<(Entity, &Player)>,
 ) {
     let input_map = InputMap::default();
-    let (player_entity, mut player) = q_player.single_mut();
-    let (debug_menu_entity, mut debug_display) = q_debug_menu.single_mut();
+    let (player_entity, player) = q_player.single();
+    let mut debug_menu_visibility = q_debug_menu.single_mut();
 
     if key.just_pressed(input_map.debug_bail) {
         ev_player_bail.send(PlayerBailEvent((player_entity, !player.bailed)));
     }
 
-    if key.just_pressed(input_map.debug_reset_position) {
-        player.set_location(Vec3::ZERO);
-    }
-
     if key.just_pressed(input_map.debug_menu) {
-        let mut visibility: Visibility = Visibility::Visible;
-        let mut debug_menu_commands = commands.entity(debug_menu_entity);
-        if debug_display.visibility == Visibility::Visible {
-            visibility = Visibility::Hidden;
-        }
-        debug_display.visibility = visibility;
-        debug_menu_commands.insert(NodeBundle {
-            visibility,
-            style: Style {
-                width: Val::Percent(100.0),
-                height: Val::Percent(100.0),
-                flex_direction: FlexDirection::Column,
-                ..default()
-            },
-            ..default()
-        });
+        *debug_menu_visibility = match *debug_menu_visibility {
+            Visibility::Hidden => Visibility::Visible,
+            _ => Visibility::Hidden,
+        };
     }
 }
 
@@ -110,9 +154,11 @@ pub fn handle_cursor(
     mouse: Res<ButtonInput<MouseButton>>,
     key: Res<ButtonInput<KeyCode>>,
 ) {
+    // Headless replays run without a window
+    let Ok(mut primary_window) = q_windows.get_single_mut() else {
+        return;
+    };
     if mouse.just_pressed(MouseButton::Left) {
-        let mut primary_window = q_windows.single_mut();
-    
         // if you want to use the cursor, but not let it leave the window,
         // use `Confined` mode:
         primary_window.cursor.grab_mode = CursorGrabMode::Confined;
@@ -126,8 +172,6 @@ pub fn handle_cursor(
     }
 
     if key.just_pressed(KeyCode::AltLeft) {
-        let mut primary_window = q_windows.single_mut();
-    
         primary_window.cursor.grab_mode = CursorGrabMode::None;
         primary_window.cursor.visible = true;
     }
@@ -1,2 +1,4 @@
 pub mod player;
 pub mod controls;
+pub mod noclip;
+pub mod replay;
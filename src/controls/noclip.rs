@@ -0,0 +1,173 @@
+use avian3d::math::PI;
+use bevy::{input::mouse::{MouseMotion, MouseWheel}, prelude::*};
+
+use crate::{entities::player::player::{Player, PlayerCamera}, utils::console::ConsoleCommandResult, Game};
+
+use super::{controls::InputMap, player::PlayerTuning};
+
+const NOCLIP_DEFAULT_SPEED: f32 = 10.0;
+const NOCLIP_MIN_SPEED: f32 = 0.5;
+const NOCLIP_MAX_SPEED: f32 = 200.0;
+const NOCLIP_SPEED_STEP: f32 = 1.2;
+const NOCLIP_MOUSE_SENSITIVITY_SCALE: f32 = 0.5;
+
+#[derive(Resource)]
+pub struct Noclip {
+    pub enabled: bool,
+    pub speed: f32,
+}
+
+#[derive(Event)]
+pub struct NoclipToggleEvent;
+
+#[derive(Component, Default)]
+pub struct NoclipCamera {
+    pub rotation: Vec3,
+}
+
+impl Default for Noclip {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            speed: NOCLIP_DEFAULT_SPEED,
+        }
+    }
+}
+
+pub fn noclip_enabled(noclip: Res<Noclip>) -> bool {
+    noclip.enabled
+}
+
+pub fn handle_noclip_keys(
+    key: Res<ButtonInput<KeyCode>>,
+    game: Res<Game>,
+    mut ev_noclip_toggle: EventWriter<NoclipToggleEvent>,
+) {
+    let input_map = InputMap::default();
+    if game.dev_mode && key.just_pressed(input_map.debug_noclip) {
+        ev_noclip_toggle.send(NoclipToggleEvent);
+    }
+}
+
+pub fn handle_noclip_toggle(
+    mut commands: Commands,
+    mut ev_noclip_toggle: EventReader<NoclipToggleEvent>,
+    mut noclip: ResMut<Noclip>,
+    mut q_player: Query<&mut Player>,
+    mut q_player_camera: Query<(&mut Camera, &GlobalTransform), (
+        With<PlayerCamera>,
+        Without<NoclipCamera>,
+    )>,
+    q_noclip_camera: Query<(Entity, &NoclipCamera, &Transform)>,
+) {
+    for _ev in ev_noclip_toggle.read() {
+        let (Ok(mut player), Ok((mut player_camera, player_camera_global_transform))) = (q_player.get_single_mut(), q_player_camera.get_single_mut()) else {
+            return;
+        };
+        noclip.enabled = !noclip.enabled;
+        player_camera.is_active = !noclip.enabled;
+
+        if noclip.enabled {
+            // Start flying from where the player camera was
+            let camera_transform = player_camera_global_transform.compute_transform();
+            let (yaw, pitch, _roll) = camera_transform.rotation.to_euler(EulerRot::YXZ);
+            commands.spawn((
+                NoclipCamera {
+                    rotation: Vec3::new(pitch, yaw, 0.0),
+                },
+                Camera3dBundle {
+                    transform: Transform {
+                        translation: camera_transform.translation,
+                        rotation: camera_transform.rotation,
+                        ..default()
+                    },
+                    ..default()
+                },
+            ));
+        } else {
+            // Teleport the player to where the camera ended up
+            for (noclip_camera_entity, noclip_camera, noclip_camera_transform) in q_noclip_camera.iter() {
+                player.set_location(noclip_camera_transform.translation);
+                player.set_rotation(Vec3::new(0.0, noclip_camera.rotation.y, 0.0));
+                player.set_velocity(Vec3::ZERO);
+                commands.entity(noclip_camera_entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+pub fn handle_noclip_camera(
+    key: Res<ButtonInput<KeyCode>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mut noclip: ResMut<Noclip>,
+    mut q_noclip_camera: Query<(&mut NoclipCamera, &mut Transform)>,
+    player_tuning: Res<PlayerTuning>,
+    time: Res<Time<Real>>,
+) {
+    let Ok((mut noclip_camera, mut noclip_camera_transform)) = q_noclip_camera.get_single_mut() else {
+        return;
+    };
+    let input_map = InputMap::default();
+    let delta = time.delta().as_secs_f32();
+
+    // Scroll to change fly speed
+    for wheel in mouse_wheel.read() {
+        noclip.speed = (noclip.speed * NOCLIP_SPEED_STEP.powf(wheel.y.signum()))
+            .clamp(NOCLIP_MIN_SPEED, NOCLIP_MAX_SPEED);
+    }
+
+    for motion in mouse_motion.read() {
+        noclip_camera.rotation.y -= motion.delta.x * player_tuning.mouse_sensitivity_x * NOCLIP_MOUSE_SENSITIVITY_SCALE;
+        noclip_camera.rotation.x -= motion.delta.y * player_tuning.mouse_sensitivity_y * NOCLIP_MOUSE_SENSITIVITY_SCALE;
+    }
+    noclip_camera.rotation.x = noclip_camera.rotation.x.clamp(-PI / 2.0, PI / 2.0);
+    let rotation_quat = Quat::from_euler(EulerRot::YXZ, noclip_camera.rotation.y, noclip_camera.rotation.x, 0.0);
+
+    // Build direction vector by keypress
+    let mut direction = Vec3::ZERO;
+    if key.pressed(input_map.forward) {
+        direction += Vec3::NEG_Z;
+    }
+    if key.pressed(input_map.back) {
+        direction += Vec3::Z;
+    }
+    if key.pressed(input_map.left) {
+        direction += Vec3::NEG_X;
+    }
+    if key.pressed(input_map.right) {
+        direction += Vec3::X;
+    }
+    direction = rotation_quat.mul_vec3(direction);
+    if key.pressed(input_map.jump) {
+        direction += Vec3::Y;
+    }
+    if key.pressed(KeyCode::ShiftLeft) {
+        direction += Vec3::NEG_Y;
+    }
+
+    noclip_camera_transform.translation += direction.normalize_or_zero() * noclip.speed * delta;
+    noclip_camera_transform.rotation = rotation_quat;
+}
+
+pub fn noclip_command(
+    In(args): In<Vec<String>>,
+    mut noclip: ResMut<Noclip>,
+    mut ev_noclip_toggle: EventWriter<NoclipToggleEvent>,
+) -> ConsoleCommandResult {
+    match args.first().map(|arg| arg.as_str()) {
+        None => {
+            ev_noclip_toggle.send(NoclipToggleEvent);
+            Ok(String::new())
+        },
+        Some("speed") => {
+            let speed: f32 = args.get(1)
+                .ok_or("missing speed")?
+                .parse()
+                .map_err(|_| "invalid speed")?;
+            noclip.speed = speed.clamp(NOCLIP_MIN_SPEED, NOCLIP_MAX_SPEED);
+            Ok(format!("Noclip speed set to {}", noclip.speed))
+        },
+        Some(arg) => Err(format!("unknown argument '{}'", arg)),
+    }
+}
@@ -4,7 +4,7 @@ use avian3d::{math::{PI, TAU}, prelude::{RayCaster, RayHits}};
 use bevy::{input::*, math::VectorSpace, prelude::*};
 use mouse::MouseMotion;
 
-use crate::entities::player::player::{Player, PlayerBody, PlayerCamera, PlayerCameraRay, PlayerFloorRay, BODY_OFFSET_VEC3, CAMERA_OFFSET_VEC3, CAMERA_RAY_OFFSET_VEC3};
+use crate::entities::player::player::{Player, PlayerBody, PlayerCamera, PlayerCameraRay, PlayerFloorRay, BODY_OFFSET_VEC3, CAMERA_OFFSET_VEC3, CAMERA_RAY_OFFSET_VEC3, VELOCITY_TIMESTEP};
 
 use super::controls::InputMap;
 
@@ -27,8 +27,76 @@ const CAMERA_TOP_DEADZONE: f32 = PI / 4.0;
 const CAMERA_BOTTOM_DEADZONE: f32 = PI / 4.0;
 
 const LEAN_SPEED: f32 = 2.0;
-const LEFT_LEAN_MAX_ANGLE: f32 = PI / 8.0;
-const RIGHT_LEAN_MAX_ANGLE: f32 = -PI / 8.0;
+const LEAN_MAX_ANGLE: f32 = PI / 8.0;
+
+/// Fraction of ground acceleration and turning that still acts on the player in the air.
+const AIR_CONTROL: f32 = 0.3;
+
+/// Runtime adjustable movement values, defaulting to the constants above.
+#[derive(Resource)]
+pub struct PlayerTuning {
+    pub gravity: f32,
+    pub jump_velocity: f32,
+    pub max_walking_velocity: f32,
+    pub turn_speed: f32,
+    pub acceleration: f32,
+    pub deceleration: f32,
+    pub mouse_sensitivity_x: f32,
+    pub mouse_sensitivity_y: f32,
+    pub lean_speed: f32,
+    pub lean_max_angle: f32,
+    pub air_control: f32,
+}
+
+impl Default for PlayerTuning {
+    fn default() -> Self {
+        Self {
+            gravity: GRAVITY.y,
+            jump_velocity: JUMP_VELOCITY.y,
+            max_walking_velocity: MAX_WALKING_VELOCITY,
+            turn_speed: TURN_SPEED,
+            acceleration: ACCELERATION,
+            deceleration: DECELERATION,
+            mouse_sensitivity_x: MOUSE_SENSITIVITY_X,
+            mouse_sensitivity_y: MOUSE_SENSITIVITY_Y,
+            lean_speed: LEAN_SPEED,
+            lean_max_angle: LEAN_MAX_ANGLE,
+            air_control: AIR_CONTROL,
+        }
+    }
+}
+
+impl PlayerTuning {
+    pub const NAMES: [&'static str; 11] = [
+        "gravity",
+        "jump_velocity",
+        "max_walking_velocity",
+        "turn_speed",
+        "acceleration",
+        "deceleration",
+        "mouse_sensitivity_x",
+        "mouse_sensitivity_y",
+        "lean_speed",
+        "lean_max_angle",
+        "air_control",
+    ];
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut f32> {
+        match name {
+            "gravity" => Some(&mut self.gravity),
+            "jump_velocity" => Some(&mut self.jump_velocity),
+            "max_walking_velocity" => Some(&mut self.max_walking_velocity),
+            "turn_speed" => Some(&mut self.turn_speed),
+            "acceleration" => Some(&mut self.acceleration),
+            "deceleration" => Some(&mut self.deceleration),
+            "mouse_sensitivity_x" => Some(&mut self.mouse_sensitivity_x),
+            "mouse_sensitivity_y" => Some(&mut self.mouse_sensitivity_y),
+            "lean_speed" => Some(&mut self.lean_speed),
+            "lean_max_angle" => Some(&mut self.lean_max_angle),
+            "air_control" => Some(&mut self.air_control),
+            _ => None,
+        }
+    }
+}
 
 pub fn handle_player_is_on_floor(
     mut q_player: Query<&mut Player>,
@@ -62,6 +130,7 @@ pub fn handle_player_movement(
         Without<Player>, 
         Without<PlayerCamera>,
     )>,
+    player_tuning: Res<PlayerTuning>,
     time: Res<Time>,
 ) {
     let (mut player, mut player_transform) = q_player.single_mut();
@@ -84,28 +153,37 @@ pub fn handle_player_movement(
     let input_map = InputMap::default();
 
     // Build direction vector by keypress
-    if current_velocity.length() < MAX_WALKING_VELOCITY && player.is_on_floor  {
+    if current_velocity.length() < player_tuning.max_walking_velocity && player.is_on_floor  {
         if keyboard_input.pressed(input_map.back) {
             direction += BACKWARD;
         }
         if keyboard_input.pressed(input_map.forward) {
             direction += FORWARD;
         }
+    } else if !player.is_on_floor {
+        // Limited steering in the air
+        if keyboard_input.pressed(input_map.back) {
+            direction += BACKWARD * player_tuning.air_control;
+        }
+        if keyboard_input.pressed(input_map.forward) {
+            direction += FORWARD * player_tuning.air_control;
+        }
     }
 
     // Turn player
     if keyboard_input.pressed(input_map.left) {
-        rotation.y += TURN_SPEED * TAU * delta;
-        current_lean = current_lean.lerp(LEFT_LEAN_MAX_ANGLE, LEAN_SPEED * delta);
+        rotation.y += player_tuning.turn_speed * TAU * delta;
+        current_lean = current_lean.lerp(player_tuning.lean_max_angle, player_tuning.lean_speed * delta);
     }
     else if keyboard_input.pressed(input_map.right) {
-        rotation.y -= TURN_SPEED * TAU * delta;
-        current_lean = current_lean.lerp(RIGHT_LEAN_MAX_ANGLE, LEAN_SPEED * delta);
+        rotation.y -= player_tuning.turn_speed * TAU * delta;
+        current_lean = current_lean.lerp(-player_tuning.lean_max_angle, player_tuning.lean_speed * delta);
     } else {
-        current_lean = current_lean.lerp(0.0, LEAN_SPEED * delta);
+        current_lean = current_lean.lerp(0.0, player_tuning.lean_speed * delta);
     }
 
     player_body.lean = current_lean;
+    let turn = rotation.y;
 
     // Add current rotation to z/y axis
     rotation.z += current_rotation.z;
@@ -125,24 +203,29 @@ pub fn handle_player_movement(
     // Multiply local direction vector by player rotation quaternion
     direction = rotation_quat.mul_vec3(direction);
     if !player.is_on_floor {
-        direction += GRAVITY;
+        direction += Vec3::new(0.0, player_tuning.gravity, 0.0);
     }
 
     // Accelerate
-    let mut velocity = current_velocity + (delta * ACCELERATION * direction);
+    let mut velocity = current_velocity + (delta * player_tuning.acceleration * direction);
+
+    // Part of the turn carries into the direction of travel while airborne
+    if !player.is_on_floor {
+        velocity = Quat::from_rotation_y(turn * player_tuning.air_control).mul_vec3(velocity);
+    }
 
     if player.is_on_floor {
         if keyboard_input.just_pressed(input_map.jump) {
-            velocity += JUMP_VELOCITY;
+            velocity += Vec3::new(0.0, player_tuning.jump_velocity, 0.0);
         } else {
-            velocity = velocity.lerp(Vec3::ZERO, delta * DECELERATION);
+            velocity = velocity.lerp(Vec3::ZERO, delta * player_tuning.deceleration);
         }
     }
     
     player.set_velocity(velocity);
 
-    // Set global position
-    let global_position = player.get_location() + velocity;
+    // Set global position, stepping velocity by how much of its timestep passed
+    let global_position = player.get_location() + velocity * delta / VELOCITY_TIMESTEP;
     player.set_location(global_position);
     
     // Apply transforms
@@ -181,6 +264,7 @@ pub fn handle_player_camera(
         Without<PlayerBody>,
         Without<PlayerCamera>,
     )>,
+    player_tuning: Res<PlayerTuning>,
     time: Res<Time>,
 ) {
     let (player, _player_transform) = q_player_transform.single_mut();
@@ -195,8 +279,8 @@ pub fn handle_player_camera(
 
     // Calculate rotations from mouse deltas
     for motion in mouse_motion.read() {
-        rotation.y -= motion.delta.x * MOUSE_SENSITIVITY_X * TAU * delta;
-        rotation.x -= motion.delta.y * MOUSE_SENSITIVITY_Y * TAU * delta;
+        rotation.y -= motion.delta.x * player_tuning.mouse_sensitivity_x * TAU * delta;
+        rotation.x -= motion.delta.y * player_tuning.mouse_sensitivity_y * TAU * delta;
     }
 
     // Clamp rotation x
@@ -231,7 +315,7 @@ pub fn handle_player_camera(
     let camera_offset_rotation_applied = camera_rotation_quat.mul_vec3(camera_offset);
     let default_camera_offset_with_rotation = camera_rotation_quat.mul_vec3(CAMERA_OFFSET_VEC3);
 
-    println!("Camera offset: {}", camera_offset_rotation_applied);
+    trace!(offset = %camera_offset_rotation_applied, "Camera offset");
 
     if player.bailed {
         // let camera_offset_rotation_applied = camera_rotation_quat.mul_vec3(camera_offset);
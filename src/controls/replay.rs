@@ -0,0 +1,380 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use avian3d::prelude::{AngularVelocity, LinearVelocity, RigidBody};
+use bevy::{input::mouse::MouseMotion, prelude::*, time::TimeUpdateStrategy};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::{entities::player::player::{Player, PlayerBody, PlayerCamera, BODY_OFFSET_VEC3}, gameplay::{grind::Grind, landing::LandingState, manual::Manual, score::Score, transition::TransitionRide, tricks::{PlayerAirtime, TrickState}}, levels::level::LevelRespawnEvent, states::game_state::GameState, utils::console::ConsoleCommandResult};
+
+/// Every frame is simulated with this delta while recording or replaying, so runs line up exactly.
+pub const REPLAY_TIMESTEP: f32 = 1.0 / 60.0;
+pub const REPLAY_DEFAULT_PATH: &str = "replay.input.ron";
+const REPLAY_ARG: &str = "--replay";
+const HEADLESS_ARG: &str = "--headless";
+
+/// Input state seen by the player systems during one frame.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct InputFrame {
+    pub pressed: Vec<KeyCode>,
+    pub mouse_motion: Vec<Vec2>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ReplayPlayerState {
+    pub location: Vec3,
+    pub rotation: Vec3,
+    pub velocity: Vec3,
+    pub camera_rotation: Vec3,
+    pub lean: f32,
+    pub bailed: bool,
+    pub is_on_floor: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct InputRecording {
+    pub timestep: f32,
+    pub start: ReplayPlayerState,
+    pub frames: Vec<InputFrame>,
+}
+
+#[derive(Default)]
+pub enum InputReplayMode {
+    #[default]
+    Idle,
+    Recording(InputRecording),
+    Playing {
+        recording: InputRecording,
+        frame: usize,
+        previous: Vec<KeyCode>,
+    },
+}
+
+#[derive(Resource, Default)]
+pub struct InputReplay {
+    pub mode: InputReplayMode,
+    /// Recording to start playing once the player is spawned.
+    pub pending: Option<PathBuf>,
+    /// Start recording at the beginning of the next frame.
+    pub pending_recording: bool,
+    /// Print the final player state and quit once playback ends.
+    pub exit_when_done: bool,
+}
+
+/// Command line options for replaying a recording, optionally without a window.
+pub struct ReplayArgs {
+    pub replay: Option<PathBuf>,
+    pub headless: bool,
+}
+
+impl ReplayArgs {
+    pub fn from_env() -> Self {
+        let args: Vec<String> = std::env::args().collect();
+        let replay = args.iter()
+            .position(|arg| arg == REPLAY_ARG)
+            .and_then(|index| args.get(index + 1))
+            .map(PathBuf::from);
+        Self {
+            headless: replay.is_some() && args.iter().any(|arg| arg == HEADLESS_ARG),
+            replay,
+        }
+    }
+}
+
+impl From<ReplayArgs> for InputReplay {
+    fn from(replay_args: ReplayArgs) -> Self {
+        Self {
+            mode: InputReplayMode::Idle,
+            pending: replay_args.replay,
+            pending_recording: false,
+            exit_when_done: replay_args.headless,
+        }
+    }
+}
+
+impl ReplayPlayerState {
+    fn from_player(player: &Player, player_camera: &PlayerCamera, player_body: &PlayerBody) -> Self {
+        Self {
+            location: player.get_location(),
+            rotation: player.get_rotation(),
+            velocity: player.get_velocity(),
+            camera_rotation: player_camera.rotation,
+            lean: player_body.lean,
+            bailed: player.bailed,
+            is_on_floor: player.is_on_floor,
+        }
+    }
+    /// Puts the player back in this state, standing the body up the same way `handle_player_bail` does.
+    fn apply(&self,
+            commands: &mut Commands,
+            player: &mut Player,
+            player_camera: &mut PlayerCamera,
+            player_body_entity: Entity,
+            player_body: &mut PlayerBody,
+            player_body_transform: &mut Transform) {
+        player.set_location(self.location);
+        player.set_rotation(self.rotation);
+        player.set_velocity(self.velocity);
+        player.bailed = self.bailed;
+        player.is_on_floor = self.is_on_floor;
+        player_camera.rotation = self.camera_rotation;
+        player_body.lean = self.lean;
+        commands.entity(player_body_entity).insert((
+            RigidBody::Kinematic,
+            LinearVelocity(Vec3::ZERO),
+            AngularVelocity(Vec3::ZERO),
+        ));
+        *player_body_transform = Transform {
+            translation: BODY_OFFSET_VEC3,
+            rotation: Quat::from_rotation_z(self.lean),
+            ..default()
+        };
+    }
+}
+
+/// Clears the run state kept outside of [`ReplayPlayerState`] and respawns the level props, so recording
+/// and playback both start from the same place. Objectives, collected items and race times are saved
+/// progress rather than run state, so they carry on as they are and can differ between the two.
+fn reset_run_state(commands: &mut Commands, ev_level_respawn: &mut EventWriter<LevelRespawnEvent>) {
+    commands.insert_resource(PlayerAirtime::default());
+    commands.insert_resource(TrickState::default());
+    commands.insert_resource(LandingState::default());
+    commands.insert_resource(TransitionRide::default());
+    commands.insert_resource(Grind::default());
+    commands.insert_resource(Manual::default());
+    commands.insert_resource(Score::default());
+    ev_level_respawn.send(LevelRespawnEvent);
+}
+
+fn load_recording(path: &PathBuf) -> Result<InputRecording, String> {
+    let serialized_recording = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let recording: InputRecording = ron::from_str(&serialized_recording).map_err(|error| error.to_string())?;
+    // Recording only starts standing, and a bailed body's physics state is not recorded to restore
+    if recording.start.bailed {
+        return Err("recording starts bailed".to_owned());
+    }
+    if !recording.timestep.is_finite() || recording.timestep <= 0.0 {
+        return Err(format!("invalid timestep {}", recording.timestep));
+    }
+    Ok(recording)
+}
+
+/// Steps every frame and the physics by exactly `timestep`, dropping fixed time left over from
+/// before so each run takes the same physics steps from its first frame.
+fn use_replay_timestep(timestep: f32, time_update_strategy: &mut TimeUpdateStrategy, fixed_time: &mut Time<Fixed>) {
+    *time_update_strategy = TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(timestep));
+    fixed_time.set_timestep_seconds(timestep as f64);
+    let overstep = fixed_time.overstep();
+    fixed_time.discard_overstep(overstep);
+}
+
+fn use_automatic_timestep(time_update_strategy: &mut TimeUpdateStrategy, fixed_time: &mut Time<Fixed>) {
+    *time_update_strategy = TimeUpdateStrategy::Automatic;
+    fixed_time.set_timestep(Time::<Fixed>::default().timestep());
+}
+
+pub fn skip_main_menu_for_replay(
+    input_replay: Res<InputReplay>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if input_replay.pending.is_some() {
+        next_state.set(GameState::Playing);
+    }
+}
+
+/// Starts a recording asked for by `record start`. Runs before time is updated, like
+/// `start_pending_replay`, so both begin on a frame that already uses the replay timestep.
+#[allow(clippy::too_many_arguments)]
+pub fn start_pending_recording(
+    mut commands: Commands,
+    mut ev_level_respawn: EventWriter<LevelRespawnEvent>,
+    mut input_replay: ResMut<InputReplay>,
+    mut time_update_strategy: ResMut<TimeUpdateStrategy>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+    q_player: Query<&Player>,
+    q_player_camera: Query<&PlayerCamera>,
+    q_player_body: Query<&PlayerBody>,
+) {
+    if !input_replay.pending_recording {
+        return;
+    }
+    input_replay.pending_recording = false;
+    let (Ok(player), Ok(player_camera), Ok(player_body)) = (q_player.get_single(), q_player_camera.get_single(), q_player_body.get_single()) else {
+        return;
+    };
+    if player.bailed {
+        warn!("Not recording input, the player bailed before recording started");
+        return;
+    }
+    reset_run_state(&mut commands, &mut ev_level_respawn);
+    use_replay_timestep(REPLAY_TIMESTEP, &mut time_update_strategy, &mut fixed_time);
+    info!("Recording input");
+    input_replay.mode = InputReplayMode::Recording(InputRecording {
+        timestep: REPLAY_TIMESTEP,
+        start: ReplayPlayerState::from_player(player, player_camera, player_body),
+        frames: Vec::new(),
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn start_pending_replay(
+    mut commands: Commands,
+    mut ev_level_respawn: EventWriter<LevelRespawnEvent>,
+    mut input_replay: ResMut<InputReplay>,
+    mut time_update_strategy: ResMut<TimeUpdateStrategy>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+    mut ev_app_exit: EventWriter<AppExit>,
+    mut q_player: Query<&mut Player>,
+    mut q_player_camera: Query<&mut PlayerCamera>,
+    mut q_player_body: Query<(Entity, &mut PlayerBody, &mut Transform)>,
+) {
+    let Some(path) = input_replay.pending.take() else {
+        return;
+    };
+    let recording = match load_recording(&path) {
+        Ok(recording) => recording,
+        Err(error) => {
+            error!(path = %path.display(), %error, "Could not load input recording");
+            if input_replay.exit_when_done {
+                ev_app_exit.send(AppExit::from_code(1));
+            }
+            return;
+        },
+    };
+    let (Ok(mut player), Ok(mut player_camera), Ok((player_body_entity, mut player_body, mut player_body_transform))) = (q_player.get_single_mut(), q_player_camera.get_single_mut(), q_player_body.get_single_mut()) else {
+        return;
+    };
+    recording.start.apply(&mut commands, &mut player, &mut player_camera, player_body_entity, &mut player_body, &mut player_body_transform);
+    reset_run_state(&mut commands, &mut ev_level_respawn);
+    use_replay_timestep(recording.timestep, &mut time_update_strategy, &mut fixed_time);
+    info!(path = %path.display(), frames = recording.frames.len(), "Replaying input");
+    input_replay.mode = InputReplayMode::Playing {
+        recording,
+        frame: 0,
+        previous: Vec::new(),
+    };
+}
+
+pub fn record_input(
+    key: Res<ButtonInput<KeyCode>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut input_replay: ResMut<InputReplay>,
+) {
+    let mouse_motion: Vec<Vec2> = mouse_motion.read().map(|motion| motion.delta).collect();
+    if let InputReplayMode::Recording(recording) = &mut input_replay.mode {
+        recording.frames.push(InputFrame {
+            pressed: key.get_pressed().copied().collect(),
+            mouse_motion,
+        });
+    }
+}
+
+/// Replaces this frame's keyboard and mouse motion input with the next recorded frame.
+#[allow(clippy::too_many_arguments)]
+pub fn play_input(
+    mut key: ResMut<ButtonInput<KeyCode>>,
+    mut mouse_motion_events: ResMut<Events<MouseMotion>>,
+    mut input_replay: ResMut<InputReplay>,
+    mut time_update_strategy: ResMut<TimeUpdateStrategy>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+    mut ev_app_exit: EventWriter<AppExit>,
+    q_player: Query<&Player>,
+    q_player_camera: Query<&PlayerCamera>,
+    q_player_body: Query<&PlayerBody>,
+) {
+    let InputReplayMode::Playing { recording, frame, previous } = &mut input_replay.mode else {
+        return;
+    };
+    if let Some(input_frame) = recording.frames.get(*frame) {
+        // Rebuild the input from last frame's keys so just pressed and released match the recording
+        key.reset_all();
+        for pressed in previous.iter() {
+            key.press(*pressed);
+            key.clear_just_pressed(*pressed);
+        }
+        for pressed in previous.iter() {
+            if !input_frame.pressed.contains(pressed) {
+                key.release(*pressed);
+            }
+        }
+        for pressed in input_frame.pressed.iter() {
+            key.press(*pressed);
+        }
+        mouse_motion_events.clear();
+        for delta in input_frame.mouse_motion.iter() {
+            mouse_motion_events.send(MouseMotion {
+                delta: *delta,
+            });
+        }
+        *previous = input_frame.pressed.to_owned();
+        *frame += 1;
+        return;
+    }
+
+    // Playback finished
+    key.reset_all();
+    use_automatic_timestep(&mut time_update_strategy, &mut fixed_time);
+    input_replay.mode = InputReplayMode::Idle;
+    let (Ok(player), Ok(player_camera), Ok(player_body)) = (q_player.get_single(), q_player_camera.get_single(), q_player_body.get_single()) else {
+        return;
+    };
+    let final_state = ReplayPlayerState::from_player(player, player_camera, player_body);
+    info!(?final_state, "Replay finished");
+    if input_replay.exit_when_done {
+        // Final state goes to stdout so test scripts can assert on it
+        match ron::ser::to_string_pretty(&final_state, PrettyConfig::default()) {
+            Ok(serialized_state) => println!("{}", serialized_state),
+            Err(error) => error!(%error, "Could not serialize final player state"),
+        }
+        ev_app_exit.send(AppExit::Success);
+    }
+}
+
+pub fn record_command(
+    In(args): In<Vec<String>>,
+    mut input_replay: ResMut<InputReplay>,
+    mut time_update_strategy: ResMut<TimeUpdateStrategy>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+    q_player: Query<&Player>,
+) -> ConsoleCommandResult {
+    match args.first().map(|arg| arg.as_str()) {
+        Some("start") => {
+            if !matches!(input_replay.mode, InputReplayMode::Idle) || input_replay.pending_recording {
+                return Err("already recording or replaying".to_owned());
+            }
+            let Ok(player) = q_player.get_single() else {
+                return Err("no player".to_owned());
+            };
+            if player.bailed {
+                return Err("stand up before recording".to_owned());
+            }
+            input_replay.pending_recording = true;
+            Ok("Recording input once the console closes".to_owned())
+        },
+        Some("stop") => {
+            let InputReplayMode::Recording(recording) = std::mem::take(&mut input_replay.mode) else {
+                return Err("not recording".to_owned());
+            };
+            use_automatic_timestep(&mut time_update_strategy, &mut fixed_time);
+            let path = args.get(1).map(|arg| arg.as_str()).unwrap_or(REPLAY_DEFAULT_PATH);
+            let serialized_recording = ron::ser::to_string_pretty(&recording, PrettyConfig::default())
+                .map_err(|error| format!("could not serialize recording: {}", error))?;
+            fs::write(path, serialized_recording)
+                .map_err(|error| format!("could not write '{}': {}", path, error))?;
+            Ok(format!("Saved {} frames to {}", recording.frames.len(), path))
+        },
+        _ => Err("expected 'start' or 'stop [path]'".to_owned()),
+    }
+}
+
+pub fn replay_command(
+    In(args): In<Vec<String>>,
+    mut input_replay: ResMut<InputReplay>,
+) -> ConsoleCommandResult {
+    if !matches!(input_replay.mode, InputReplayMode::Idle) || input_replay.pending_recording {
+        return Err("already recording or replaying".to_owned());
+    }
+    let path = args.first().map(|arg| arg.as_str()).unwrap_or(REPLAY_DEFAULT_PATH);
+    input_replay.pending = Some(PathBuf::from(path));
+    Ok(format!("Replaying {}", path))
+}
@@ -0,0 +1,508 @@
+use std::{fs, path::Path};
+
+use avian3d::{math::{PI, TAU}, prelude::{CollisionLayers, LayerMask, RigidBody, SpatialQuery, SpatialQueryFilter}};
+use bevy::{color::palettes::css::{BLUE, GREEN, RED, YELLOW}, input::mouse::MouseMotion, prelude::*, window::{CursorGrabMode, PrimaryWindow}};
+use ron::ser::PrettyConfig;
+
+use crate::{controls::controls::InputMap, entities::{player::player::PlayerCamera, EntityCollisionLayers}, levels::level::{CurrentLevel, Level, LevelBody, LevelMaterial, LevelObject, LevelShape}, states::loading::GameAssets, Game};
+
+const EDITOR_FLY_SPEED: f32 = 10.0;
+const EDITOR_FAST_FLY_MULTIPLIER: f32 = 4.0;
+const EDITOR_MOUSE_SENSITIVITY: f32 = 0.003;
+const EDITOR_SELECT_DISTANCE: f32 = 500.0;
+const EDITOR_SPAWN_DISTANCE: f32 = 5.0;
+const EDITOR_TRANSLATE_SPEED: f32 = 0.05;
+const EDITOR_ROTATE_SPEED: f32 = 0.01;
+const EDITOR_SCALE_SPEED: f32 = 0.01;
+const EDITOR_MIN_SCALE: f32 = 0.01;
+const EDITOR_GIZMO_SIZE: f32 = 2.0;
+
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub enum EditorTool {
+    #[default]
+    Translate,
+    Rotate,
+    Scale,
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub enum EditorAxis {
+    #[default]
+    X,
+    Y,
+    Z,
+}
+
+#[derive(Resource, Default)]
+pub struct Editor {
+    pub enabled: bool,
+    pub selected: Option<Entity>,
+    pub tool: EditorTool,
+    pub axis: EditorAxis,
+    pub palette_index: usize,
+}
+
+#[derive(Component, Default)]
+pub struct EditorCamera {
+    pub rotation: Vec3,
+}
+
+#[derive(Component)]
+pub struct EditorStatusDisplay;
+
+impl EditorAxis {
+    pub fn to_vec3(self) -> Vec3 {
+        match self {
+            EditorAxis::X => Vec3::X,
+            EditorAxis::Y => Vec3::Y,
+            EditorAxis::Z => Vec3::Z,
+        }
+    }
+    pub fn color(self) -> Color {
+        match self {
+            EditorAxis::X => RED.into(),
+            EditorAxis::Y => GREEN.into(),
+            EditorAxis::Z => BLUE.into(),
+        }
+    }
+}
+
+/// Objects that can be placed from the editor, cycled with the palette keys.
+pub fn editor_palette() -> Vec<LevelObject> {
+    let prop_material = LevelMaterial {
+        color: (124, 144, 255),
+        ..default()
+    };
+    let palette_object = |name: &str, shape: LevelShape, body: LevelBody, layer: EntityCollisionLayers| LevelObject {
+        name: Some(name.to_owned()),
+        shape,
+        location: Vec3::ZERO,
+        rotation: Vec3::ZERO,
+        scale: Vec3::ONE,
+        body,
+        layer,
+        material: prop_material.clone(),
+        friction: None,
+        angular_velocity: None,
+        count: 1,
+    };
+    vec![
+        palette_object("Cube", LevelShape::Cuboid { x: 1.0, y: 1.0, z: 1.0 }, LevelBody::Static, EntityCollisionLayers::Ground),
+        palette_object("Sphere", LevelShape::Sphere { radius: 0.5 }, LevelBody::Static, EntityCollisionLayers::Ground),
+        palette_object("Cylinder", LevelShape::Cylinder { radius: 0.5, height: 1.0 }, LevelBody::Static, EntityCollisionLayers::Ground),
+        palette_object("Cone", LevelShape::Cone { radius: 0.5, height: 1.0 }, LevelBody::Static, EntityCollisionLayers::Ground),
+        palette_object("Capsule", LevelShape::Capsule { radius: 0.5, length: 1.0 }, LevelBody::Static, EntityCollisionLayers::Ground),
+        palette_object("Ramp", LevelShape::Ramp { width: 3.0, length: 4.0, height: 1.0 }, LevelBody::Static, EntityCollisionLayers::Ground),
+        palette_object("QuarterPipe", LevelShape::QuarterPipe { radius: 3.0, width: 6.0, height: 3.5 }, LevelBody::Static, EntityCollisionLayers::Ground),
+        palette_object("HalfPipe", LevelShape::HalfPipe { radius: 3.0, width: 8.0, height: 3.5, flat: 4.0 }, LevelBody::Static, EntityCollisionLayers::Ground),
+        palette_object("Prop Box", LevelShape::Cuboid { x: 1.0, y: 1.0, z: 1.0 }, LevelBody::Dynamic, EntityCollisionLayers::Props),
+        palette_object("Generator", LevelShape::Gltf { path: "models/Generator.glb".to_owned() }, LevelBody::Static, EntityCollisionLayers::Ground),
+    ]
+}
+
+pub fn editor_enabled(editor: Res<Editor>) -> bool {
+    editor.enabled
+}
+
+pub fn handle_editor_toggle(
+    mut commands: Commands,
+    key: Res<ButtonInput<KeyCode>>,
+    game_assets: Res<GameAssets>,
+    game: Res<Game>,
+    mut editor: ResMut<Editor>,
+    mut q_windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut q_player_camera: Query<(&mut Camera, &GlobalTransform), (
+        With<PlayerCamera>,
+        Without<EditorCamera>,
+    )>,
+    q_editor_entities: Query<Entity, Or<(With<EditorCamera>, With<EditorStatusDisplay>)>>,
+) {
+    let input_map = InputMap::default();
+    if !game.dev_mode || !key.just_pressed(input_map.editor_toggle) {
+        return;
+    }
+    let Ok((mut player_camera, player_camera_global_transform)) = q_player_camera.get_single_mut() else {
+        return;
+    };
+    // Headless replays run without a window, so there is nothing to edit in
+    let Ok(mut primary_window) = q_windows.get_single_mut() else {
+        return;
+    };
+    editor.enabled = !editor.enabled;
+    editor.selected = None;
+    player_camera.is_active = !editor.enabled;
+
+    if !editor.enabled {
+        for editor_entity in q_editor_entities.iter() {
+            commands.entity(editor_entity).despawn_recursive();
+        }
+        return;
+    }
+
+    // Free the cursor so entities can be clicked on
+    primary_window.cursor.grab_mode = CursorGrabMode::None;
+    primary_window.cursor.visible = true;
+
+    // Start the fly camera where the player camera was
+    let camera_transform = player_camera_global_transform.compute_transform();
+    let (yaw, pitch, _roll) = camera_transform.rotation.to_euler(EulerRot::YXZ);
+    commands.spawn((
+        EditorCamera {
+            rotation: Vec3::new(pitch, yaw, 0.0),
+        },
+        Camera3dBundle {
+            transform: Transform {
+                translation: camera_transform.translation,
+                rotation: camera_transform.rotation,
+                ..default()
+            },
+            ..default()
+        },
+    ));
+    commands.spawn((
+        EditorStatusDisplay,
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: game_assets.debug_font.clone(),
+                font_size: 16.0,
+                ..default()
+            },
+        ).with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        }),
+    ));
+}
+
+pub fn handle_editor_camera(
+    key: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut q_editor_camera: Query<(&mut EditorCamera, &mut Transform)>,
+    time: Res<Time<Real>>,
+) {
+    let Ok((mut editor_camera, mut editor_camera_transform)) = q_editor_camera.get_single_mut() else {
+        return;
+    };
+    let input_map = InputMap::default();
+    let delta = time.delta().as_secs_f32();
+
+    // Look around while the right mouse button is held
+    if mouse.pressed(MouseButton::Right) {
+        for motion in mouse_motion.read() {
+            editor_camera.rotation.y -= motion.delta.x * EDITOR_MOUSE_SENSITIVITY;
+            editor_camera.rotation.x -= motion.delta.y * EDITOR_MOUSE_SENSITIVITY;
+        }
+        editor_camera.rotation.x = editor_camera.rotation.x.clamp(-PI / 2.0, PI / 2.0);
+        editor_camera.rotation.y %= TAU;
+    } else {
+        mouse_motion.clear();
+    }
+    let rotation_quat = Quat::from_euler(EulerRot::YXZ, editor_camera.rotation.y, editor_camera.rotation.x, 0.0);
+
+    // Build direction vector by keypress, manipulation steals the modifier key
+    let mut direction = Vec3::ZERO;
+    if !key.pressed(input_map.editor_manipulate) {
+        if key.pressed(input_map.forward) {
+            direction += Vec3::NEG_Z;
+        }
+        if key.pressed(input_map.back) {
+            direction += Vec3::Z;
+        }
+        if key.pressed(input_map.left) {
+            direction += Vec3::NEG_X;
+        }
+        if key.pressed(input_map.right) {
+            direction += Vec3::X;
+        }
+    }
+    direction = rotation_quat.mul_vec3(direction);
+    if key.pressed(input_map.editor_up) {
+        direction += Vec3::Y;
+    }
+    if key.pressed(input_map.editor_down) {
+        direction += Vec3::NEG_Y;
+    }
+
+    let mut speed = EDITOR_FLY_SPEED;
+    if key.pressed(KeyCode::ShiftRight) {
+        speed *= EDITOR_FAST_FLY_MULTIPLIER;
+    }
+
+    editor_camera_transform.translation += direction.normalize_or_zero() * speed * delta;
+    editor_camera_transform.rotation = rotation_quat;
+}
+
+pub fn handle_editor_select(
+    mouse: Res<ButtonInput<MouseButton>>,
+    key: Res<ButtonInput<KeyCode>>,
+    mut editor: ResMut<Editor>,
+    spatial_query: SpatialQuery,
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    q_editor_camera: Query<(&Camera, &GlobalTransform), With<EditorCamera>>,
+    q_level_objects: Query<(), With<LevelObject>>,
+    q_parents: Query<&Parent>,
+) {
+    let input_map = InputMap::default();
+    if !mouse.just_pressed(MouseButton::Left) || key.pressed(input_map.editor_manipulate) {
+        return;
+    }
+    let Ok((editor_camera, editor_camera_global_transform)) = q_editor_camera.get_single() else {
+        return;
+    };
+    let Some(cursor_position) = q_windows.get_single().ok().and_then(|primary_window| primary_window.cursor_position()) else {
+        return;
+    };
+    let Some(ray) = editor_camera.viewport_to_world(editor_camera_global_transform, cursor_position) else {
+        return;
+    };
+
+    let hit = spatial_query.cast_ray(
+        ray.origin,
+        ray.direction,
+        EDITOR_SELECT_DISTANCE,
+        true,
+        SpatialQueryFilter {
+            mask: [
+                EntityCollisionLayers::Ground,
+                EntityCollisionLayers::Interaction,
+                EntityCollisionLayers::Props,
+            ].into(),
+            ..default()
+        },
+    );
+
+    // Colliders of glTF scenes live on descendants, so walk up to the level object
+    editor.selected = hit.and_then(|hit| {
+        let mut entity = hit.entity;
+        loop {
+            if q_level_objects.contains(entity) {
+                return Some(entity);
+            }
+            entity = q_parents.get(entity).ok()?.get();
+        }
+    });
+}
+
+pub fn handle_editor_transform(
+    key: Res<ButtonInput<KeyCode>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut editor: ResMut<Editor>,
+    mut q_level_objects: Query<(&mut LevelObject, &mut Transform)>,
+) {
+    let input_map = InputMap::default();
+
+    if key.just_pressed(input_map.editor_translate) {
+        editor.tool = EditorTool::Translate;
+    }
+    if key.just_pressed(input_map.editor_rotate) {
+        editor.tool = EditorTool::Rotate;
+    }
+    if key.just_pressed(input_map.editor_scale) {
+        editor.tool = EditorTool::Scale;
+    }
+    if key.just_pressed(input_map.editor_axis_x) {
+        editor.axis = EditorAxis::X;
+    }
+    if key.just_pressed(input_map.editor_axis_y) {
+        editor.axis = EditorAxis::Y;
+    }
+    if key.just_pressed(input_map.editor_axis_z) {
+        editor.axis = EditorAxis::Z;
+    }
+
+    let Some((mut level_object, mut transform)) = editor.selected
+        .and_then(|selected| q_level_objects.get_mut(selected).ok()) else {
+        mouse_motion.clear();
+        return;
+    };
+    if !key.pressed(input_map.editor_manipulate) {
+        mouse_motion.clear();
+        return;
+    }
+
+    // Horizontal mouse movement drives the active tool along the active axis
+    let amount: f32 = mouse_motion.read().map(|motion| motion.delta.x).sum();
+    if amount == 0.0 {
+        return;
+    }
+    let axis = editor.axis.to_vec3();
+    match editor.tool {
+        EditorTool::Translate => {
+            transform.translation += axis * amount * EDITOR_TRANSLATE_SPEED;
+        },
+        EditorTool::Rotate => {
+            transform.rotate_axis(Dir3::new(axis).unwrap(), amount * EDITOR_ROTATE_SPEED);
+        },
+        EditorTool::Scale => {
+            transform.scale = (transform.scale + axis * amount * EDITOR_SCALE_SPEED).max(Vec3::splat(EDITOR_MIN_SCALE));
+        },
+    }
+
+    // Keep the saved description in sync with the edit
+    let (rotation_x, rotation_y, rotation_z) = transform.rotation.to_euler(EulerRot::XYZ);
+    level_object.location = transform.translation;
+    level_object.rotation = Vec3::new(rotation_x, rotation_y, rotation_z);
+    level_object.scale = transform.scale;
+}
+
+pub fn handle_editor_palette(
+    mut commands: Commands,
+    key: Res<ButtonInput<KeyCode>>,
+    asset_server: Res<AssetServer>,
+    mut editor: ResMut<Editor>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut q_level_objects: Query<&mut LevelObject>,
+    q_editor_camera: Query<&Transform, With<EditorCamera>>,
+) {
+    let input_map = InputMap::default();
+    let palette = editor_palette();
+
+    if key.just_pressed(input_map.editor_palette_next) {
+        editor.palette_index = (editor.palette_index + 1) % palette.len();
+    }
+    if key.just_pressed(input_map.editor_palette_previous) {
+        editor.palette_index = (editor.palette_index + palette.len() - 1) % palette.len();
+    }
+
+    if key.just_pressed(input_map.editor_spawn) {
+        if let Ok(editor_camera_transform) = q_editor_camera.get_single() {
+            let location = editor_camera_transform.translation
+                + editor_camera_transform.forward() * EDITOR_SPAWN_DISTANCE;
+            let level_object = LevelObject {
+                location,
+                ..palette[editor.palette_index].clone()
+            };
+            editor.selected = Some(level_object.spawn(commands.reborrow(),
+                &asset_server,
+                meshes.reborrow(),
+                materials.reborrow()));
+        }
+    }
+
+    let Some(selected) = editor.selected else {
+        return;
+    };
+    let Ok(mut level_object) = q_level_objects.get_mut(selected) else {
+        editor.selected = None;
+        return;
+    };
+
+    if key.just_pressed(input_map.editor_delete) {
+        commands.entity(selected).despawn_recursive();
+        editor.selected = None;
+        return;
+    }
+
+    if key.just_pressed(input_map.editor_cycle_body) {
+        level_object.body = match level_object.body {
+            LevelBody::Static => LevelBody::Dynamic,
+            LevelBody::Dynamic => LevelBody::Kinematic,
+            LevelBody::Kinematic => LevelBody::Static,
+        };
+        commands.entity(selected).insert(RigidBody::from(level_object.body));
+    }
+
+    if key.just_pressed(input_map.editor_cycle_layer) {
+        level_object.layer = match level_object.layer {
+            EntityCollisionLayers::Ground => EntityCollisionLayers::Interaction,
+            EntityCollisionLayers::Interaction => EntityCollisionLayers::Props,
+            _ => EntityCollisionLayers::Ground,
+        };
+        commands.entity(selected).insert(CollisionLayers::new(level_object.layer, LayerMask::ALL));
+    }
+}
+
+pub fn handle_editor_save(
+    key: Res<ButtonInput<KeyCode>>,
+    current_level: Res<CurrentLevel>,
+    levels: Res<Assets<Level>>,
+    q_level_objects: Query<&LevelObject>,
+) {
+    let input_map = InputMap::default();
+    if !key.just_pressed(input_map.editor_save) {
+        return;
+    }
+    let (Some(level), Some(level_path)) = (levels.get(&current_level.handle), current_level.handle.path()) else {
+        warn!("No level loaded to save");
+        return;
+    };
+
+    // Spawn points, gates, collectibles, rails, lights and objectives are not editable yet, so keep them from the loaded file
+    let level = Level {
+        objects: q_level_objects.iter().cloned().collect(),
+        ..level.clone()
+    };
+
+    let file_path = Path::new("assets").join(level_path.path());
+    match ron::ser::to_string_pretty(&level, PrettyConfig::default()) {
+        Ok(serialized_level) => match fs::write(&file_path, serialized_level) {
+            Ok(()) => info!(path = %file_path.display(), "Saved level"),
+            Err(error) => error!(path = %file_path.display(), %error, "Could not write level"),
+        },
+        Err(error) => error!(%error, "Could not serialize level"),
+    }
+}
+
+pub fn draw_editor_gizmos(
+    mut gizmos: Gizmos,
+    editor: Res<Editor>,
+    q_transforms: Query<&GlobalTransform, With<LevelObject>>,
+) {
+    let Some(global_transform) = editor.selected.and_then(|selected| q_transforms.get(selected).ok()) else {
+        return;
+    };
+    let transform = global_transform.compute_transform();
+    let position = transform.translation;
+
+    for axis in [EditorAxis::X, EditorAxis::Y, EditorAxis::Z] {
+        let color = if axis == editor.axis {
+            YELLOW.into()
+        } else {
+            axis.color()
+        };
+        match editor.tool {
+            EditorTool::Translate => {
+                gizmos.arrow(position, position + axis.to_vec3() * EDITOR_GIZMO_SIZE, color);
+            },
+            EditorTool::Rotate => {
+                gizmos.circle(position, Dir3::new(axis.to_vec3()).unwrap(), EDITOR_GIZMO_SIZE, color);
+            },
+            EditorTool::Scale => {
+                let end = position + transform.rotation.mul_vec3(axis.to_vec3()) * EDITOR_GIZMO_SIZE;
+                gizmos.line(position, end, color);
+                gizmos.cuboid(Transform::from_translation(end).with_scale(Vec3::splat(0.2)), color);
+            },
+        }
+    }
+}
+
+pub fn update_editor_status(
+    editor: Res<Editor>,
+    mut q_editor_status_display: Query<&mut Text, With<EditorStatusDisplay>>,
+    q_level_objects: Query<(&LevelObject, Option<&Name>)>,
+) {
+    let Ok(mut editor_status_text) = q_editor_status_display.get_single_mut() else {
+        return;
+    };
+    let palette = editor_palette();
+    let selected = editor.selected
+        .and_then(|selected| q_level_objects.get(selected).ok())
+        .map(|(level_object, name)| format!("{} ({:?}, {:?})",
+            name.map(|name| name.as_str()).unwrap_or("Unnamed"),
+            level_object.body,
+            level_object.layer))
+        .unwrap_or("None".to_owned());
+
+    editor_status_text.sections[0].value = format!(
+        "EDITOR  Tool: {:?}  Axis: {:?}  Palette: {}\nSelected: {}",
+        editor.tool,
+        editor.axis,
+        palette[editor.palette_index].name.as_deref().unwrap_or("Unnamed"),
+        selected
+    );
+}
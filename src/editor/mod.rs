@@ -0,0 +1 @@
+pub mod editor;
\ No newline at end of file
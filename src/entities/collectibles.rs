@@ -0,0 +1,186 @@
+use std::collections::HashSet;
+
+use avian3d::prelude::CollisionStarted;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{entities::player::player::PlayerBody, gameplay::save::{level_save_path, read_save, write_save}, levels::level::{CurrentLevel, Level}};
+
+/// Kind of save file collected ids are kept in.
+const COLLECTIBLES_SAVE_KIND: &str = "collectibles";
+/// Turn rate of a collectible waiting to be picked up, in radians per second.
+const COLLECTIBLE_SPIN_SPEED: f32 = 2.0;
+
+/// What happens to a collectible once the player has picked it up.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum CollectibleRespawn {
+    /// Gone for good, and left out of the level in later sessions.
+    #[default]
+    Never,
+    /// Comes back this many seconds after being picked up.
+    After(f32),
+}
+
+/// Pickup on the collectibles sensor layer, collected when the player body touches it.
+#[derive(Component)]
+pub struct Collectible {
+    pub id: String,
+    pub respawn: CollectibleRespawn,
+    /// Seconds left until a picked up collectible comes back.
+    pub respawn_timer: Option<f32>,
+}
+
+/// Which collectibles of the current level have been picked up, at least once.
+#[derive(Resource, Default)]
+pub struct CollectedItems {
+    pub collected: HashSet<String>,
+    pub total: usize,
+}
+
+/// Ids of the collected collectibles on a level, as saved to disk.
+#[derive(Serialize, Deserialize, Default)]
+pub struct CollectiblesSave {
+    pub collected: Vec<String>,
+}
+
+#[derive(Event)]
+pub struct CollectiblePickedUpEvent {
+    pub id: String,
+    /// Whether this is the first time it has been picked up.
+    pub first: bool,
+}
+
+/// Reads back which collectibles were picked up whenever the current level file loads or changes.
+pub fn load_collected_items(
+    mut ev_level_asset: EventReader<AssetEvent<Level>>,
+    mut collected_items: ResMut<CollectedItems>,
+    current_level: Res<CurrentLevel>,
+    levels: Res<Assets<Level>>,
+) {
+    let mut reload = false;
+    for ev in ev_level_asset.read() {
+        if ev.is_loaded_with_dependencies(&current_level.handle)
+            || ev.is_modified(&current_level.handle) {
+            reload = true;
+        }
+    }
+    if !reload {
+        return;
+    }
+    let Some(level) = levels.get(&current_level.handle) else {
+        return;
+    };
+    let collectibles_save = level_save_path(&current_level, COLLECTIBLES_SAVE_KIND)
+        .map(|path| read_save::<CollectiblesSave>(&path))
+        .transpose()
+        .unwrap_or_else(|error| {
+            warn!("Could not load collected items: {}", error);
+            None
+        })
+        .flatten()
+        .unwrap_or_default();
+
+    *collected_items = CollectedItems {
+        collected: collectibles_save.collected.into_iter().collect(),
+        total: level.collectibles.len(),
+    };
+}
+
+/// Clears collectibles that were picked up for good in an earlier session out of a freshly spawned level.
+pub fn remove_collected_items(
+    mut commands: Commands,
+    collected_items: Res<CollectedItems>,
+    q_collectibles: Query<(Entity, &Collectible), Added<Collectible>>,
+) {
+    for (entity, collectible) in q_collectibles.iter() {
+        if collectible.respawn == CollectibleRespawn::Never && collected_items.collected.contains(&collectible.id) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+pub fn handle_collectibles(
+    mut commands: Commands,
+    mut ev_collision_started: EventReader<CollisionStarted>,
+    mut ev_collectible_picked_up: EventWriter<CollectiblePickedUpEvent>,
+    mut collected_items: ResMut<CollectedItems>,
+    current_level: Res<CurrentLevel>,
+    q_player_body: Query<(), With<PlayerBody>>,
+    mut q_collectibles: Query<(&mut Collectible, &mut Visibility)>,
+) {
+    let mut save = false;
+    for CollisionStarted(entity1, entity2) in ev_collision_started.read() {
+        let collectible_entity = if q_player_body.contains(*entity1) {
+            *entity2
+        } else if q_player_body.contains(*entity2) {
+            *entity1
+        } else {
+            continue;
+        };
+        let Ok((mut collectible, mut visibility)) = q_collectibles.get_mut(collectible_entity) else {
+            continue;
+        };
+        // Waiting to come back
+        if collectible.respawn_timer.is_some() {
+            continue;
+        }
+        let first = collected_items.collected.insert(collectible.id.to_owned());
+        info!(id = %collectible.id, count = collected_items.collected.len(), total = collected_items.total, "Collectible picked up");
+        ev_collectible_picked_up.send(CollectiblePickedUpEvent {
+            id: collectible.id.to_owned(),
+            first,
+        });
+        save |= first;
+        match collectible.respawn {
+            CollectibleRespawn::Never => commands.entity(collectible_entity).despawn_recursive(),
+            CollectibleRespawn::After(seconds) => {
+                collectible.respawn_timer = Some(seconds);
+                *visibility = Visibility::Hidden;
+            },
+        }
+    }
+    if !save {
+        return;
+    }
+
+    let Some(path) = level_save_path(&current_level, COLLECTIBLES_SAVE_KIND) else {
+        return;
+    };
+    let mut collectibles_save = CollectiblesSave {
+        collected: collected_items.collected.iter().cloned().collect(),
+    };
+    // Keep the file stable between saves
+    collectibles_save.collected.sort();
+    if let Err(error) = write_save(&path, &collectibles_save) {
+        warn!("Could not save collected items: {}", error);
+    }
+}
+
+/// Brings picked up collectibles back once their respawn time is up.
+pub fn update_collectible_respawns(
+    mut q_collectibles: Query<(&mut Collectible, &mut Visibility)>,
+    time: Res<Time>,
+) {
+    let delta = time.delta().as_secs_f32();
+    for (mut collectible, mut visibility) in q_collectibles.iter_mut() {
+        let Some(respawn_timer) = collectible.respawn_timer else {
+            continue;
+        };
+        if respawn_timer > delta {
+            collectible.respawn_timer = Some(respawn_timer - delta);
+            continue;
+        }
+        debug!(id = %collectible.id, "Collectible respawned");
+        collectible.respawn_timer = None;
+        *visibility = Visibility::Inherited;
+    }
+}
+
+pub fn spin_collectibles(
+    mut q_collectibles: Query<&mut Transform, With<Collectible>>,
+    time: Res<Time>,
+) {
+    for mut transform in q_collectibles.iter_mut() {
+        transform.rotate_y(COLLECTIBLE_SPIN_SPEED * time.delta().as_secs_f32());
+    }
+}
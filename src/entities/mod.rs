@@ -1,12 +1,28 @@
 use avian3d::prelude::PhysicsLayer;
+use serde::{Deserialize, Serialize};
 
+pub mod collectibles;
 pub mod player;
 pub mod world_objects;
 
-#[derive(PhysicsLayer)]
+#[derive(PhysicsLayer, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
 pub enum EntityCollisionLayers {
+    #[default]
     Ground,
     Interaction,
     Player,
     Props,
-}
\ No newline at end of file
+    Triggers,
+    Collectibles,
+}
+
+impl EntityCollisionLayers {
+    pub const ALL: [EntityCollisionLayers; 6] = [
+        EntityCollisionLayers::Ground,
+        EntityCollisionLayers::Interaction,
+        EntityCollisionLayers::Player,
+        EntityCollisionLayers::Props,
+        EntityCollisionLayers::Triggers,
+        EntityCollisionLayers::Collectibles,
+    ];
+}
@@ -3,11 +3,13 @@ use std::fmt;
 use avian3d::prelude::{AngularVelocity, Collider, CollisionLayers, Dominance, LayerMask, LinearVelocity, PhysicsLayer, RayCaster, RigidBody, SpatialQueryFilter};
 use bevy::{math::*, prelude::*};
 
-use crate::entities::EntityCollisionLayers;
+use crate::entities::{world_objects::PlayerRespawn, EntityCollisionLayers};
 
 pub const CAMERA_OFFSET_VEC3: Vec3 = Vec3::new(0.0, 1.0, 10.0);
 pub const CAMERA_RAY_OFFSET_VEC3: Vec3 = Vec3::new(0.0, 1.0, 0.0);
 pub const BODY_OFFSET_VEC3: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+/// Time `Player` velocity is an offset over, so the player covers the same ground at any frame rate or time scale.
+pub const VELOCITY_TIMESTEP: f32 = 1.0 / 60.0;
 
 #[derive(Component, Default)]
 pub struct Player {
@@ -53,6 +55,10 @@ impl Player {
     pub fn get_velocity(&self) -> Vec3 {
         return self.velocity;
     }
+    /// Speed in meters per second.
+    pub fn get_speed(&self) -> f32 {
+        return self.velocity.length() / VELOCITY_TIMESTEP;
+    }
     pub fn set_location(&mut self, new_location: Vec3) {
         self.location = new_location;
     }
@@ -130,7 +136,9 @@ impl Player {
                 Collider::capsule(0.5, 1.0),
                 CollisionLayers::new(EntityCollisionLayers::Player, [
                     EntityCollisionLayers::Ground,
-                    EntityCollisionLayers::Props
+                    EntityCollisionLayers::Props,
+                    EntityCollisionLayers::Triggers,
+                    EntityCollisionLayers::Collectibles,
                 ]),
                 Dominance(5),
                 PbrBundle {
@@ -177,31 +185,38 @@ pub fn handle_player_bail(
         Without<Player>, 
         Without<PlayerCamera>,
     )>,
-    time: Res<Time>,
+    mut player_respawn: ResMut<PlayerRespawn>,
 ) {
     let (mut player, mut _player_transform) = q_player_transform.single_mut();
     let (mut player_body, player_body_entity, mut player_body_transform, player_body_global_transform) = q_player_body_transform.single_mut();
     let mut player_body_entity = commands.entity(player_body_entity);
-    let delta = time.delta().as_secs_f32();
     for ev in ev_player_bail.read() {
-        let bailed = ev.0.1;
+        let (bailed_entity, bailed) = ev.0;
+        let _bail_span = info_span!("player_bail", entity = ?bailed_entity).entered();
         player.bailed = bailed;
         if bailed {
             player_body_entity.insert(RigidBody::Dynamic);
             let current_velocity = player.get_velocity();
-            println!("Player bailed! {}", current_velocity);
+            info!(velocity = %current_velocity, "Player bailed");
 
             // Create player rotation quaternion from rotation y value
-            let body_velocity = current_velocity / delta;
+            let body_velocity = current_velocity / VELOCITY_TIMESTEP;
             player.set_velocity(Vec3::ZERO);
             player_body_entity.insert(LinearVelocity(body_velocity));
         } else {
-            println!("Player standing up!");
+            info!("Player standing up");
             player_body_entity.insert(RigidBody::Kinematic);
             player_body_entity.insert(AngularVelocity(Vec3::ZERO));
             player_body_entity.insert(LinearVelocity(Vec3::ZERO));
             player_body.lean = 0.0;
-            player.set_location(player_body_global_transform.translation() - BODY_OFFSET_VEC3);
+            if player_respawn.pending {
+                player_respawn.pending = false;
+                player.set_location(player_respawn.location);
+                player.set_rotation(player_respawn.rotation);
+                player.set_velocity(Vec3::ZERO);
+            } else {
+                player.set_location(player_body_global_transform.translation() - BODY_OFFSET_VEC3);
+            }
         }
     }
 }
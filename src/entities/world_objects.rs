@@ -1 +1,81 @@
-use bevy::prelude::Component;
\ No newline at end of file
+use avian3d::prelude::CollisionStarted;
+use bevy::prelude::*;
+
+use crate::{controls::controls::InputMap, entities::player::player::{Player, PlayerBailEvent, PlayerBody}, gameplay::{grind::Grind, transition::TransitionRide}};
+
+/// Location a player can be placed at when the level starts.
+#[derive(Component)]
+pub struct SpawnPoint {
+    pub rotation: Vec3,
+    pub default: bool,
+}
+
+/// Trigger volume that moves the respawn position when the player touches it.
+#[derive(Component)]
+pub struct Checkpoint {
+    pub rotation: Vec3,
+}
+
+#[derive(Resource, Default)]
+pub struct PlayerRespawn {
+    pub location: Vec3,
+    pub rotation: Vec3,
+    pub checkpoint: Option<Entity>,
+    /// Stand the player up here instead of where the body landed.
+    pub pending: bool,
+}
+
+pub fn handle_checkpoints(
+    mut ev_collision_started: EventReader<CollisionStarted>,
+    mut player_respawn: ResMut<PlayerRespawn>,
+    q_player_body: Query<(), With<PlayerBody>>,
+    q_checkpoints: Query<(&Checkpoint, &GlobalTransform)>,
+) {
+    for CollisionStarted(entity1, entity2) in ev_collision_started.read() {
+        let checkpoint_entity = if q_player_body.contains(*entity1) {
+            *entity2
+        } else if q_player_body.contains(*entity2) {
+            *entity1
+        } else {
+            continue;
+        };
+        let Ok((checkpoint, checkpoint_transform)) = q_checkpoints.get(checkpoint_entity) else {
+            continue;
+        };
+        if player_respawn.checkpoint == Some(checkpoint_entity) {
+            continue;
+        }
+        info!(location = %checkpoint_transform.translation(), "Checkpoint reached");
+        player_respawn.location = checkpoint_transform.translation();
+        player_respawn.rotation = checkpoint.rotation;
+        player_respawn.checkpoint = Some(checkpoint_entity);
+    }
+}
+
+/// Sends the player back to the last checkpoint, or the spawn point before one is reached.
+/// This bails first when standing, so the run ends the way any other bail ends it, then stands
+/// the player up at the respawn position.
+pub fn handle_player_respawn(
+    key: Res<ButtonInput<KeyCode>>,
+    mut ev_player_bail: EventWriter<PlayerBailEvent>,
+    mut player_respawn: ResMut<PlayerRespawn>,
+    mut grind: ResMut<Grind>,
+    mut transition_ride: ResMut<TransitionRide>,
+    q_player: Query<(Entity, &Player)>,
+) {
+    let input_map = InputMap::default();
+    if !key.just_pressed(input_map.respawn) {
+        return;
+    }
+    let Ok((player_entity, player)) = q_player.get_single() else {
+        return;
+    };
+    info!(location = %player_respawn.location, "Respawning player");
+    grind.position = None;
+    transition_ride.transition = None;
+    if !player.bailed {
+        ev_player_bail.send(PlayerBailEvent((player_entity, true)));
+    }
+    ev_player_bail.send(PlayerBailEvent((player_entity, false)));
+    player_respawn.pending = true;
+}
\ No newline at end of file
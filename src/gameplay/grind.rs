@@ -0,0 +1,277 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{controls::{controls::InputMap, player::PlayerTuning}, entities::player::player::{Player, PlayerBailEvent, PlayerBody, BODY_OFFSET_VEC3, VELOCITY_TIMESTEP}};
+
+use super::tricks::PlayerAirtime;
+
+/// How far from the feet a rail can be and still be snapped onto.
+const GRIND_SNAP_DISTANCE: f32 = 0.6;
+/// Minimum cosine between horizontal travel and the rail for a grind to start.
+const GRIND_MIN_ALIGNMENT: f32 = 0.8;
+const GRIND_SNAP_COOLDOWN: f32 = 0.3;
+const GRIND_FRICTION: f32 = 1.5;
+const GRIND_SLOPE_ACCELERATION: f32 = 9.81;
+const GRIND_MIN_SPEED: f32 = 1.0;
+/// Lean at which balance is lost, in radians.
+const GRIND_BALANCE_LIMIT: f32 = 0.8;
+const GRIND_BALANCE_INSTABILITY: f32 = 3.0;
+const GRIND_BALANCE_CORRECTION: f32 = 4.0;
+const GRIND_BALANCE_DAMPING: f32 = 1.5;
+const GRIND_DRIFT_STRENGTH: f32 = 0.6;
+const GRIND_DRIFT_FREQUENCY: f32 = 1.7;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum GrindableKind {
+    #[default]
+    Rail,
+    Ledge,
+}
+
+/// A rail or ledge the player can grind along, as a world space polyline.
+#[derive(Component)]
+pub struct Grindable {
+    pub points: Vec<Vec3>,
+    pub kind: GrindableKind,
+}
+
+#[derive(Clone, Copy)]
+pub struct GrindPosition {
+    pub rail: Entity,
+    pub segment: usize,
+    /// Distance along the segment from its first point.
+    pub distance: f32,
+    /// +1 when travelling towards the end of the polyline, -1 towards its start.
+    pub direction: f32,
+}
+
+#[derive(Resource, Default)]
+pub struct Grind {
+    pub position: Option<GrindPosition>,
+    /// Speed along the rail, in meters per second.
+    pub speed: f32,
+    pub balance: f32,
+    pub balance_velocity: f32,
+    pub duration: f32,
+    cooldown: f32,
+}
+
+#[derive(Event)]
+pub struct GrindStartedEvent {
+    pub kind: GrindableKind,
+}
+
+#[derive(Event)]
+pub struct GrindEndedEvent {
+    pub kind: GrindableKind,
+    pub duration: f32,
+    pub bailed: bool,
+}
+
+impl Grindable {
+    fn segment(&self, segment: usize) -> (Vec3, Vec3) {
+        (self.points[segment], self.points[segment + 1])
+    }
+    /// Closest point to `location` as (segment, distance along it, distance to it).
+    pub fn closest(&self, location: Vec3) -> Option<(usize, f32, f32)> {
+        (0..self.points.len().saturating_sub(1))
+            .map(|segment| {
+                let (start, end) = self.segment(segment);
+                let length = start.distance(end);
+                let along = (location - start).dot((end - start).normalize_or_zero()).clamp(0.0, length);
+                let point = start + (end - start).normalize_or_zero() * along;
+                (segment, along, point.distance(location))
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+    }
+}
+
+pub fn player_grinding(grind: Res<Grind>) -> bool {
+    grind.position.is_some()
+}
+
+pub fn handle_grind_snap(
+    mut ev_grind_started: EventWriter<GrindStartedEvent>,
+    mut grind: ResMut<Grind>,
+    player_airtime: Res<PlayerAirtime>,
+    mut q_player: Query<&mut Player>,
+    q_grindables: Query<(Entity, &Grindable)>,
+    time: Res<Time>,
+) {
+    let delta = time.delta().as_secs_f32();
+    grind.cooldown = (grind.cooldown - delta).max(0.0);
+    let Ok(mut player) = q_player.get_single_mut() else {
+        return;
+    };
+    let velocity = player.get_velocity();
+    if grind.position.is_some() || grind.cooldown > 0.0 || player.bailed
+        || !player_airtime.airborne || velocity.y > 0.0 || delta <= 0.0 {
+        return;
+    }
+    let horizontal_velocity = velocity * Vec3::new(1.0, 0.0, 1.0);
+    let location = player.get_location();
+
+    let candidate = q_grindables.iter()
+        .filter_map(|(entity, grindable)| grindable.closest(location)
+            .map(|(segment, distance, offset)| (entity, grindable, segment, distance, offset)))
+        .filter(|(_entity, _grindable, _segment, _distance, offset)| *offset < GRIND_SNAP_DISTANCE)
+        .min_by(|a, b| a.4.total_cmp(&b.4));
+    let Some((rail, grindable, segment, distance, _offset)) = candidate else {
+        return;
+    };
+
+    // Only snap when travelling along the rail, not across it
+    let (start, end) = grindable.segment(segment);
+    let rail_direction = (end - start) * Vec3::new(1.0, 0.0, 1.0);
+    let alignment = horizontal_velocity.normalize_or_zero().dot(rail_direction.normalize_or_zero());
+    if alignment.abs() < GRIND_MIN_ALIGNMENT {
+        return;
+    }
+
+    // Player velocity is an offset over the velocity timestep, the grind runs in meters per second
+    grind.speed = horizontal_velocity.length() / VELOCITY_TIMESTEP;
+    grind.balance = 0.0;
+    grind.balance_velocity = 0.0;
+    grind.duration = 0.0;
+    grind.position = Some(GrindPosition {
+        rail,
+        segment,
+        distance,
+        direction: alignment.signum(),
+    });
+    player.is_on_floor = true;
+    info!(kind = ?grindable.kind, "Grind started");
+    ev_grind_started.send(GrindStartedEvent {
+        kind: grindable.kind,
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_grind(
+    key: Res<ButtonInput<KeyCode>>,
+    mut ev_grind_ended: EventWriter<GrindEndedEvent>,
+    mut ev_player_bail: EventWriter<PlayerBailEvent>,
+    mut grind: ResMut<Grind>,
+    player_tuning: Res<PlayerTuning>,
+    mut q_player: Query<(Entity, &mut Player, &mut Transform), Without<PlayerBody>>,
+    mut q_player_body: Query<(&mut PlayerBody, &mut Transform), Without<Player>>,
+    q_grindables: Query<&Grindable>,
+    time: Res<Time>,
+) {
+    let Some(grind_position) = grind.position else {
+        return;
+    };
+    let (Ok((player_entity, mut player, mut player_transform)), Ok((mut player_body, mut player_body_transform))) = (q_player.get_single_mut(), q_player_body.get_single_mut()) else {
+        return;
+    };
+    let delta = time.delta().as_secs_f32();
+    if delta <= 0.0 {
+        return;
+    }
+    let input_map = InputMap::default();
+    let Ok(grindable) = q_grindables.get(grind_position.rail) else {
+        grind.position = None;
+        return;
+    };
+    let kind = grindable.kind;
+    let (mut segment, mut distance, direction) = (grind_position.segment, grind_position.distance, grind_position.direction);
+
+    // Slide along the rail, slowed by friction and sped up going downhill
+    let (start, end) = grindable.segment(segment);
+    let travel = (end - start).normalize_or_zero() * direction;
+    grind.speed += (-travel.y * GRIND_SLOPE_ACCELERATION - GRIND_FRICTION) * delta;
+    distance += grind.speed * direction * delta;
+
+    // Carry over onto the next segment, or run off the end
+    let mut off_end = false;
+    loop {
+        let (start, end) = grindable.segment(segment);
+        let length = start.distance(end);
+        if distance > length {
+            if segment + 2 >= grindable.points.len() {
+                off_end = true;
+                distance = length;
+                break;
+            }
+            distance -= length;
+            segment += 1;
+        } else if distance < 0.0 {
+            if segment == 0 {
+                off_end = true;
+                distance = 0.0;
+                break;
+            }
+            segment -= 1;
+            let (start, end) = grindable.segment(segment);
+            distance += start.distance(end);
+        } else {
+            break;
+        }
+    }
+    let (start, end) = grindable.segment(segment);
+    let travel = (end - start).normalize_or_zero() * direction;
+    let location = start + (end - start).normalize_or_zero() * distance;
+
+    // Balance drifts and tips further the more it is off, left and right push back.
+    // Drift follows time on the rail rather than app time so replays play out the same.
+    grind.duration += delta;
+    let drift = (grind.duration * GRIND_DRIFT_FREQUENCY).sin() * GRIND_DRIFT_STRENGTH;
+    let mut balance_acceleration = grind.balance * GRIND_BALANCE_INSTABILITY + drift - grind.balance_velocity * GRIND_BALANCE_DAMPING;
+    if key.pressed(input_map.left) {
+        balance_acceleration += GRIND_BALANCE_CORRECTION;
+    }
+    if key.pressed(input_map.right) {
+        balance_acceleration -= GRIND_BALANCE_CORRECTION;
+    }
+    grind.balance_velocity += balance_acceleration * delta;
+    grind.balance += grind.balance_velocity * delta;
+
+    // Knocked off by something else, e.g. the bail command
+    let knocked_off = player.bailed;
+    if !knocked_off {
+        // Follow the rail
+        let rotation_y = f32::atan2(-travel.x, -travel.z);
+        player.set_rotation(Vec3::new(0.0, rotation_y, 0.0));
+        player.set_location(location);
+        player.set_velocity(travel * grind.speed * VELOCITY_TIMESTEP);
+        player_body.lean = grind.balance;
+        *player_transform = Transform {
+            translation: location,
+            rotation: Quat::from_rotation_y(rotation_y),
+            ..default()
+        };
+        *player_body_transform = Transform {
+            translation: BODY_OFFSET_VEC3,
+            rotation: Quat::from_rotation_z(player_body.lean),
+            ..default()
+        };
+    }
+    let bailed = !knocked_off && grind.balance.abs() > GRIND_BALANCE_LIMIT;
+    let jumped = key.just_pressed(input_map.jump);
+    if !(knocked_off || bailed || jumped || off_end || grind.speed < GRIND_MIN_SPEED) {
+        grind.position = Some(GrindPosition {
+            rail: grind_position.rail,
+            segment,
+            distance,
+            direction,
+        });
+        return;
+    }
+
+    // Leave the rail
+    if jumped {
+        player.set_velocity(player.get_velocity() + Vec3::new(0.0, player_tuning.jump_velocity, 0.0));
+    }
+    player.is_on_floor = false;
+    info!(kind = ?kind, duration = grind.duration, bailed = knocked_off || bailed, "Grind ended");
+    if bailed {
+        ev_player_bail.send(PlayerBailEvent((player_entity, true)));
+    }
+    ev_grind_ended.send(GrindEndedEvent {
+        kind,
+        duration: grind.duration,
+        bailed: knocked_off || bailed,
+    });
+    grind.position = None;
+    grind.cooldown = GRIND_SNAP_COOLDOWN;
+}
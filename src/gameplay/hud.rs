@@ -0,0 +1,466 @@
+use bevy::prelude::*;
+
+use crate::{entities::{collectibles::CollectedItems, player::player::Player}, states::{game_state::GameState, loading::GameAssets}, utils::ui::set_section_text};
+
+use super::{grind::{GrindStartedEvent, GrindableKind}, manual::{Manual, MANUAL_BALANCE_LIMIT}, objectives::{handle_objectives, ObjectiveCompletedEvent, Objectives}, race::{format_race_time, handle_race_gates, Race, RaceFinishedEvent, RaceSplitEvent}, score::{handle_score_bail, Score}, tricks::TrickLandedEvent};
+
+const HUD_MARGIN: f32 = 12.0;
+const HUD_FONT_SIZE: f32 = 28.0;
+const POPUP_FONT_SIZE: f32 = 40.0;
+const BAIL_FONT_SIZE: f32 = 56.0;
+const POPUP_COLOR: Color = Color::srgb(1.0, 0.85, 0.3);
+const BAIL_COLOR: Color = Color::srgb(0.9, 0.2, 0.2);
+/// How long a trick name stays up, in seconds.
+const POPUP_DURATION: f32 = 1.5;
+/// Time spent shrinking from the pop-in scale, in seconds.
+const POPUP_GROW_TIME: f32 = 0.2;
+/// Time spent fading out at the end, in seconds.
+const POPUP_FADE_TIME: f32 = 0.5;
+const POPUP_SCALE: f32 = 1.5;
+const MANUAL_METER_WIDTH: f32 = 200.0;
+const MANUAL_METER_HEIGHT: f32 = 12.0;
+const MANUAL_METER_MARKER_WIDTH: f32 = 6.0;
+const MANUAL_METER_BACKGROUND_COLOR: Color = Color::srgba(0.15, 0.15, 0.15, 0.8);
+const MANUAL_METER_MARKER_COLOR: Color = Color::WHITE;
+const OBJECTIVE_FONT_SIZE: f32 = 20.0;
+const OBJECTIVE_COMPLETED_COLOR: Color = Color::srgb(0.3, 0.9, 0.3);
+const RACE_AHEAD_COLOR: Color = Color::srgb(0.3, 0.9, 0.3);
+const RACE_BEHIND_COLOR: Color = Color::srgb(0.9, 0.3, 0.3);
+
+/// Player facing overlay, shown in every build unlike the debug display.
+pub struct HudPlugin;
+
+#[derive(Component)]
+pub struct ScoreDisplay;
+
+#[derive(Component)]
+pub struct SpeedDisplay;
+
+/// Collectibles picked up on the level out of how many there are.
+#[derive(Component)]
+pub struct CollectibleDisplay;
+
+#[derive(Component)]
+pub struct TrickPopup;
+
+#[derive(Component)]
+pub struct BailIndicator;
+
+#[derive(Component)]
+pub struct ManualMeter;
+
+/// One line per level objective, under the score.
+#[derive(Component)]
+pub struct ObjectiveList;
+
+/// Race timer, with the last split or result underneath.
+#[derive(Component)]
+pub struct RaceDisplay;
+
+#[derive(Component)]
+pub struct ManualMeterMarker;
+
+/// The move last shown in the pop-up and how long it has been up.
+#[derive(Resource, Default)]
+pub struct TrickPopupState {
+    pub text: String,
+    pub age: f32,
+}
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TrickPopupState {
+                age: POPUP_DURATION,
+                ..default()
+            })
+            .add_systems(OnEnter(GameState::Playing), (setup_hud, setup_manual_meter))
+            .add_systems(Update, (
+                update_score_display.after(handle_score_bail),
+                update_speed_display,
+                update_collectible_display,
+                handle_trick_popup.after(handle_objectives),
+                update_trick_popup.after(handle_trick_popup),
+                update_bail_indicator,
+                update_manual_meter,
+                update_race_display.after(handle_race_gates),
+                update_objective_list.after(handle_objectives),
+            ).run_if(in_state(GameState::Playing)));
+    }
+}
+
+pub fn setup_hud(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+) {
+    let text_style = TextStyle {
+        font: game_assets.menu_font.clone(),
+        font_size: HUD_FONT_SIZE,
+        ..default()
+    };
+    // Full width rows so centred text stays centred whatever its length
+    let centred_row = |top: f32| NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(top),
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        ..default()
+    };
+
+    commands.spawn((
+        StateScoped(GameState::Playing),
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            ..default()
+        },
+    )).with_children(|parent| {
+        parent.spawn((
+            ScoreDisplay,
+            TextBundle {
+                text: Text::from_sections([
+                    TextSection::new("", text_style.clone()),
+                    TextSection::new("", text_style.clone()),
+                ]).with_justify(JustifyText::Right),
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(HUD_MARGIN),
+                    right: Val::Px(HUD_MARGIN),
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+        parent.spawn((
+            ObjectiveList,
+            TextBundle {
+                text: Text::default().with_justify(JustifyText::Right),
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Percent(15.0),
+                    right: Val::Px(HUD_MARGIN),
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+        parent.spawn((
+            SpeedDisplay,
+            TextBundle {
+                text: Text::from_section("", text_style.clone()),
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(HUD_MARGIN),
+                    left: Val::Px(HUD_MARGIN),
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+        parent.spawn(NodeBundle {
+            style: Style {
+                top: Val::Px(HUD_MARGIN),
+                ..centred_row(0.0).style
+            },
+            ..default()
+        }).with_children(|parent| {
+            parent.spawn((
+                RaceDisplay,
+                TextBundle::from_sections([
+                    TextSection::new("", text_style.clone()),
+                    TextSection::new("", text_style.clone()),
+                ]).with_text_justify(JustifyText::Center),
+            ));
+        });
+        parent.spawn((
+            CollectibleDisplay,
+            TextBundle {
+                text: Text::from_section("", text_style.clone()),
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(HUD_MARGIN),
+                    right: Val::Px(HUD_MARGIN),
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+        parent.spawn(centred_row(20.0)).with_children(|parent| {
+            parent.spawn((
+                TrickPopup,
+                TextBundle::from_section("", TextStyle {
+                    font_size: POPUP_FONT_SIZE,
+                    color: POPUP_COLOR,
+                    ..text_style.clone()
+                }),
+            ));
+        });
+        parent.spawn(centred_row(40.0)).with_children(|parent| {
+            parent.spawn((
+                BailIndicator,
+                TextBundle {
+                    text: Text::from_section("BAILED", TextStyle {
+                        font_size: BAIL_FONT_SIZE,
+                        color: BAIL_COLOR,
+                        ..text_style.clone()
+                    }),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+            ));
+        });
+    });
+}
+
+pub fn update_score_display(
+    score: Res<Score>,
+    mut q_score_display: Query<&mut Text, With<ScoreDisplay>>,
+) {
+    for mut text in q_score_display.iter_mut() {
+        let total = format!("Score: {}\n", score.total);
+        let combo = if score.in_combo() {
+            format!("{} x {}", score.combo_points as u32, score.combo_multiplier)
+        } else {
+            String::new()
+        };
+        set_section_text(&mut text, 0, total);
+        set_section_text(&mut text, 1, combo);
+    }
+}
+
+pub fn update_speed_display(
+    q_player: Query<&Player>,
+    mut q_speed_display: Query<&mut Text, With<SpeedDisplay>>,
+) {
+    let Ok(player) = q_player.get_single() else {
+        return;
+    };
+    let speed = player.get_speed();
+    for mut text in q_speed_display.iter_mut() {
+        set_section_text(&mut text, 0, format!("{:.1} m/s", speed));
+    }
+}
+
+pub fn update_collectible_display(
+    collected_items: Res<CollectedItems>,
+    mut q_collectible_display: Query<&mut Text, With<CollectibleDisplay>>,
+) {
+    // Levels without collectibles get no counter
+    let count = if collected_items.total > 0 {
+        format!("Tokens {}/{}", collected_items.collected.len(), collected_items.total)
+    } else {
+        String::new()
+    };
+    for mut text in q_collectible_display.iter_mut() {
+        set_section_text(&mut text, 0, count.to_owned());
+    }
+}
+
+pub fn handle_trick_popup(
+    mut ev_trick_landed: EventReader<TrickLandedEvent>,
+    mut ev_grind_started: EventReader<GrindStartedEvent>,
+    mut ev_objective_completed: EventReader<ObjectiveCompletedEvent>,
+    mut trick_popup_state: ResMut<TrickPopupState>,
+) {
+    for ev in ev_trick_landed.read() {
+        trick_popup_state.text = format!("{} +{}", ev.name, ev.points);
+        trick_popup_state.age = 0.0;
+    }
+    for ev in ev_grind_started.read() {
+        trick_popup_state.text = match ev.kind {
+            GrindableKind::Rail => "Rail Grind".to_owned(),
+            GrindableKind::Ledge => "Ledge Grind".to_owned(),
+        };
+        trick_popup_state.age = 0.0;
+    }
+    // Completing an objective outranks whatever move finished it
+    for ev in ev_objective_completed.read() {
+        trick_popup_state.text = format!("Completed: {}", ev.name);
+        trick_popup_state.age = 0.0;
+    }
+}
+
+/// Pops the trick name in large, settles it and fades it out.
+pub fn update_trick_popup(
+    mut trick_popup_state: ResMut<TrickPopupState>,
+    mut q_trick_popup: Query<(&mut Text, &mut Transform), With<TrickPopup>>,
+    time: Res<Time>,
+) {
+    trick_popup_state.age = (trick_popup_state.age + time.delta().as_secs_f32()).min(POPUP_DURATION);
+    let age = trick_popup_state.age;
+    let grow = (age / POPUP_GROW_TIME).min(1.0);
+    let scale = POPUP_SCALE + (1.0 - POPUP_SCALE) * grow * (2.0 - grow);
+    let alpha = ((POPUP_DURATION - age) / POPUP_FADE_TIME).clamp(0.0, 1.0);
+    let color = POPUP_COLOR.with_alpha(alpha);
+    for (mut text, mut transform) in q_trick_popup.iter_mut() {
+        set_section_text(&mut text, 0, trick_popup_state.text.to_owned());
+        // Colour and scale are only written while they change, so settled text is not laid out again
+        if text.sections[0].style.color != color {
+            text.sections[0].style.color = color;
+        }
+        transform.set_if_neq(Transform::from_scale(Vec3::splat(scale)));
+    }
+}
+
+pub fn update_bail_indicator(
+    q_player: Query<&Player>,
+    mut q_bail_indicator: Query<&mut Visibility, With<BailIndicator>>,
+) {
+    let bailed = q_player.get_single().is_ok_and(|player| player.bailed);
+    for mut visibility in q_bail_indicator.iter_mut() {
+        *visibility = if bailed {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+pub fn setup_manual_meter(
+    mut commands: Commands,
+) {
+    commands.spawn((
+        ManualMeter,
+        StateScoped(GameState::Playing),
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(48.0),
+                left: Val::Percent(50.0),
+                margin: UiRect::left(Val::Px(-MANUAL_METER_WIDTH / 2.0)),
+                width: Val::Px(MANUAL_METER_WIDTH),
+                height: Val::Px(MANUAL_METER_HEIGHT),
+                ..default()
+            },
+            background_color: MANUAL_METER_BACKGROUND_COLOR.into(),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+    )).with_children(|parent| {
+        parent.spawn((
+            ManualMeterMarker,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Percent(50.0),
+                    margin: UiRect::left(Val::Px(-MANUAL_METER_MARKER_WIDTH / 2.0)),
+                    width: Val::Px(MANUAL_METER_MARKER_WIDTH),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                background_color: MANUAL_METER_MARKER_COLOR.into(),
+                ..default()
+            },
+        ));
+    });
+}
+
+pub fn update_manual_meter(
+    manual: Res<Manual>,
+    mut q_manual_meter: Query<&mut Visibility, With<ManualMeter>>,
+    mut q_manual_meter_marker: Query<&mut Style, With<ManualMeterMarker>>,
+) {
+    for mut visibility in q_manual_meter.iter_mut() {
+        *visibility = if manual.is_active() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+    let balance = (manual.balance / MANUAL_BALANCE_LIMIT).clamp(-1.0, 1.0);
+    for mut style in q_manual_meter_marker.iter_mut() {
+        style.left = Val::Percent(50.0 + balance * 50.0);
+    }
+}
+
+pub fn update_objective_list(
+    objectives: Res<Objectives>,
+    game_assets: Res<GameAssets>,
+    mut q_objective_list: Query<&mut Text, With<ObjectiveList>>,
+) {
+    let lines: Vec<(String, Color)> = objectives.objectives.iter()
+        .map(|objective| {
+            if objective.completed {
+                return (format!("[x] {}\n", objective.name), OBJECTIVE_COMPLETED_COLOR);
+            }
+            let goal = &objective.goal;
+            // Rounded down so nearly there is not shown as done
+            let precision = goal.precision();
+            let scale = 10f32.powi(precision as i32);
+            let progress = (objective.progress * scale).floor() / scale;
+            let mut line = format!("[ ] {}  {:.*}/{:.*}{}", objective.name, precision, progress, precision, goal.target(), goal.unit());
+            if let Some(time_left) = objective.time_left() {
+                line += &format!("  {:.0}s", time_left.ceil());
+            }
+            (line + "\n", Color::WHITE)
+        })
+        .collect();
+    for mut text in q_objective_list.iter_mut() {
+        // Sections follow the objectives, which only change when the level does
+        if text.sections.len() != lines.len() {
+            text.sections = lines.iter()
+                .map(|_line| TextSection::new("", TextStyle {
+                    font: game_assets.menu_font.clone(),
+                    font_size: OBJECTIVE_FONT_SIZE,
+                    ..default()
+                }))
+                .collect();
+        }
+        for (section, (line, color)) in lines.iter().enumerate() {
+            set_section_text(&mut text, section, line.to_owned());
+            if text.sections[section].style.color != *color {
+                text.sections[section].style.color = *color;
+            }
+        }
+    }
+}
+
+pub fn update_race_display(
+    race: Res<Race>,
+    mut ev_race_split: EventReader<RaceSplitEvent>,
+    mut ev_race_finished: EventReader<RaceFinishedEvent>,
+    mut q_race_display: Query<&mut Text, With<RaceDisplay>>,
+) {
+    let timer = if let Some(run) = race.run.as_ref() {
+        format_race_time(run.elapsed)
+    } else if let Some(last_time) = race.last_time {
+        format!("Finish {}", format_race_time(last_time))
+    } else if let Some(personal_best) = race.personal_best.as_ref() {
+        format!("Best {}", format_race_time(personal_best.time))
+    } else {
+        String::new()
+    };
+    // Splits and results stay up until the next one, a fresh run clears them
+    let mut result = race.run.as_ref()
+        .filter(|run| run.splits.is_empty())
+        .map(|_run| (String::new(), Color::WHITE));
+    for ev in ev_race_split.read() {
+        result = Some(match ev.difference {
+            Some(difference) => (
+                format!("\nGate {} {:+.2}", ev.gate, difference),
+                if difference <= 0.0 { RACE_AHEAD_COLOR } else { RACE_BEHIND_COLOR },
+            ),
+            None => (format!("\nGate {} {}", ev.gate, format_race_time(ev.time)), Color::WHITE),
+        });
+    }
+    for ev in ev_race_finished.read() {
+        if ev.personal_best {
+            result = Some(("\nNew personal best!".to_owned(), RACE_AHEAD_COLOR));
+        }
+    }
+    for mut text in q_race_display.iter_mut() {
+        set_section_text(&mut text, 0, timer.to_owned());
+        let Some((value, color)) = result.as_ref() else {
+            continue;
+        };
+        set_section_text(&mut text, 1, value.to_owned());
+        if text.sections[1].style.color != *color {
+            text.sections[1].style.color = *color;
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use avian3d::{math::PI, prelude::{SpatialQuery, SpatialQueryFilter}};
+use bevy::prelude::*;
+
+use crate::{entities::{player::player::{Player, PlayerBailEvent, PlayerBody}, EntityCollisionLayers}, utils::math::wrap_angle};
+
+use super::{grind::Grind, transition::TransitionRide, tricks::{PlayerAirtime, PlayerLandedEvent, TrickFailedEvent, TrickState}};
+
+/// How far below the feet the ground is looked for before lining the player up.
+const LANDING_ASSIST_DISTANCE: f32 = 2.0;
+/// Misalignment beyond this is left for the player to fix, in radians.
+const LANDING_ASSIST_MAX_ANGLE: f32 = 0.6;
+const LANDING_ASSIST_SPEED: f32 = 3.0;
+/// Height above the feet the ground normal is looked for from when landing.
+const LANDING_PROBE_HEIGHT: f32 = 0.5;
+const LANDING_PROBE_DISTANCE: f32 = 1.5;
+/// Worst tilt or slip, in radians, still landing at each quality.
+const LANDING_PERFECT_ANGLE: f32 = 0.15;
+const LANDING_GOOD_ANGLE: f32 = 0.35;
+const LANDING_SKETCHY_ANGLE: f32 = 0.7;
+/// Horizontal speed below which the direction of travel is not judged, as an offset per frame.
+const LANDING_MIN_SLIP_SPEED: f32 = 0.01;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LandingQuality {
+    Perfect,
+    Good,
+    Sketchy,
+    Bail,
+}
+
+#[derive(Event)]
+pub struct PlayerLandingEvent {
+    pub quality: LandingQuality,
+    /// Angle between the body up axis and the ground normal, in radians.
+    pub tilt: f32,
+    /// Angle between the board and the direction of travel, either way round, in radians.
+    pub slip: f32,
+}
+
+/// Velocity from the last airborne frame, since touching down zeroes the fall.
+#[derive(Resource, Default)]
+pub struct LandingState {
+    pub air_velocity: Vec3,
+}
+
+impl LandingQuality {
+    fn from_angle(angle: f32) -> Self {
+        if angle <= LANDING_PERFECT_ANGLE {
+            LandingQuality::Perfect
+        } else if angle <= LANDING_GOOD_ANGLE {
+            LandingQuality::Good
+        } else if angle <= LANDING_SKETCHY_ANGLE {
+            LandingQuality::Sketchy
+        } else {
+            LandingQuality::Bail
+        }
+    }
+}
+
+/// Yaw change that lines the board up with `velocity`, rolling forwards or fakie, whichever is closer.
+fn alignment_yaw(yaw: f32, velocity: Vec3) -> f32 {
+    let travel_yaw = f32::atan2(-velocity.x, -velocity.z);
+    let forwards = wrap_angle(travel_yaw - yaw);
+    let fakie = wrap_angle(travel_yaw + PI - yaw);
+    if forwards.abs() <= fakie.abs() {
+        forwards
+    } else {
+        fakie
+    }
+}
+
+fn ground_filter() -> SpatialQueryFilter {
+    SpatialQueryFilter {
+        mask: EntityCollisionLayers::Ground.into(),
+        ..default()
+    }
+}
+
+/// Eases the board straight and in line with the direction of travel just before touching down.
+pub fn handle_landing_assist(
+    player_airtime: Res<PlayerAirtime>,
+    trick_state: Res<TrickState>,
+    spatial_query: SpatialQuery,
+    mut q_player: Query<&mut Player>,
+    mut q_player_body: Query<&mut PlayerBody>,
+    time: Res<Time>,
+) {
+    let (Ok(mut player), Ok(mut player_body)) = (q_player.get_single_mut(), q_player_body.get_single_mut()) else {
+        return;
+    };
+    let velocity = player.get_velocity();
+    if !player_airtime.airborne || velocity.y >= 0.0 || trick_state.active.is_some() {
+        return;
+    }
+    let ground = spatial_query.cast_ray(
+        player.get_location(),
+        Dir3::NEG_Y,
+        LANDING_ASSIST_DISTANCE,
+        true,
+        ground_filter(),
+    );
+    if ground.is_none() {
+        return;
+    }
+
+    let max_step = LANDING_ASSIST_SPEED * time.delta().as_secs_f32();
+    let horizontal_velocity = velocity * Vec3::new(1.0, 0.0, 1.0);
+    let mut rotation = player.get_rotation();
+    if horizontal_velocity.length() > LANDING_MIN_SLIP_SPEED {
+        let correction = alignment_yaw(rotation.y, horizontal_velocity);
+        if correction.abs() <= LANDING_ASSIST_MAX_ANGLE {
+            rotation.y += correction.clamp(-max_step, max_step);
+            player.set_rotation(rotation);
+        }
+    }
+    player_body.lean -= player_body.lean.clamp(-max_step, max_step);
+}
+
+/// Judges each touchdown by how upright and how in line with the direction of travel the board is.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_landing_quality(
+    mut ev_player_landed: EventReader<PlayerLandedEvent>,
+    mut ev_trick_failed: EventReader<TrickFailedEvent>,
+    mut ev_player_landing: EventWriter<PlayerLandingEvent>,
+    mut ev_player_bail: EventWriter<PlayerBailEvent>,
+    mut landing_state: ResMut<LandingState>,
+    player_airtime: Res<PlayerAirtime>,
+    grind: Res<Grind>,
+    transition_ride: Res<TransitionRide>,
+    spatial_query: SpatialQuery,
+    q_player: Query<(Entity, &Player)>,
+    q_player_body: Query<&GlobalTransform, With<PlayerBody>>,
+) {
+    let (Ok((player_entity, player)), Ok(player_body_global_transform)) = (q_player.get_single(), q_player_body.get_single()) else {
+        return;
+    };
+    // A failed trick has already bailed the player
+    let trick_failed = ev_trick_failed.read().count() > 0;
+    if player_airtime.airborne {
+        landing_state.air_velocity = player.get_velocity();
+    }
+    for _ev in ev_player_landed.read() {
+        // Grinds keep their own balance and transitions line the board up themselves
+        if trick_failed || player.bailed || grind.position.is_some() || transition_ride.transition.is_some() {
+            continue;
+        }
+        let location = player.get_location();
+        let normal = spatial_query.cast_ray(
+            location + Vec3::Y * LANDING_PROBE_HEIGHT,
+            Dir3::NEG_Y,
+            LANDING_PROBE_DISTANCE,
+            true,
+            ground_filter(),
+        ).map_or(Vec3::Y, |hit| hit.normal);
+
+        let tilt = player_body_global_transform.up().angle_between(normal);
+        let velocity = landing_state.air_velocity;
+        let travel = velocity - normal * velocity.dot(normal);
+        let slip = if travel.length() > LANDING_MIN_SLIP_SPEED {
+            let forward = player_body_global_transform.forward();
+            let board = *forward - normal * forward.dot(normal);
+            let angle = board.angle_between(travel);
+            angle.min(PI - angle)
+        } else {
+            0.0
+        };
+        let quality = LandingQuality::from_angle(tilt.max(slip));
+        info!(?quality, tilt, slip, "Landed");
+        if quality == LandingQuality::Bail {
+            ev_player_bail.send(PlayerBailEvent((player_entity, true)));
+        }
+        ev_player_landing.send(PlayerLandingEvent {
+            quality,
+            tilt,
+            slip,
+        });
+    }
+}
@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+
+use crate::{controls::controls::InputMap, entities::player::player::{Player, PlayerBailEvent, PlayerBody}};
+
+use super::grind::Grind;
+
+/// How quickly the second direction has to follow the first to start a manual.
+const MANUAL_INPUT_WINDOW: f32 = 0.25;
+/// Body pitch held during a manual, in radians.
+const MANUAL_PITCH: f32 = 0.3;
+/// Balance at which the player goes over, in radians.
+pub const MANUAL_BALANCE_LIMIT: f32 = 0.6;
+const MANUAL_BALANCE_INSTABILITY: f32 = 2.5;
+const MANUAL_BALANCE_CORRECTION: f32 = 3.0;
+const MANUAL_BALANCE_DAMPING: f32 = 1.5;
+const MANUAL_DRIFT_STRENGTH: f32 = 0.5;
+const MANUAL_DRIFT_FREQUENCY: f32 = 1.3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ManualKind {
+    /// On the back wheels, started with back then forward.
+    Manual,
+    /// On the front wheels, started with forward then back.
+    NoseManual,
+}
+
+#[derive(Resource, Default)]
+pub struct Manual {
+    pub kind: Option<ManualKind>,
+    /// Positive tips towards the nose.
+    pub balance: f32,
+    pub balance_velocity: f32,
+    pub duration: f32,
+    last_direction: Option<(KeyCode, f32)>,
+}
+
+#[derive(Event)]
+pub struct ManualEndedEvent {
+    pub kind: ManualKind,
+    pub duration: f32,
+    pub bailed: bool,
+}
+
+impl ManualKind {
+    fn pitch(self) -> f32 {
+        match self {
+            ManualKind::Manual => MANUAL_PITCH,
+            ManualKind::NoseManual => -MANUAL_PITCH,
+        }
+    }
+}
+
+impl Manual {
+    pub fn is_active(&self) -> bool {
+        self.kind.is_some()
+    }
+    fn end(&mut self, ev_manual_ended: &mut EventWriter<ManualEndedEvent>, bailed: bool) {
+        let Some(kind) = self.kind.take() else {
+            return;
+        };
+        info!(?kind, duration = self.duration, bailed, "Manual ended");
+        ev_manual_ended.send(ManualEndedEvent {
+            kind,
+            duration: self.duration,
+            bailed,
+        });
+    }
+}
+
+pub fn handle_manual(
+    key: Res<ButtonInput<KeyCode>>,
+    mut ev_manual_ended: EventWriter<ManualEndedEvent>,
+    mut ev_player_bail: EventWriter<PlayerBailEvent>,
+    mut manual: ResMut<Manual>,
+    grind: Res<Grind>,
+    q_player: Query<(Entity, &Player)>,
+    time: Res<Time>,
+) {
+    let Ok((player_entity, player)) = q_player.get_single() else {
+        return;
+    };
+    let delta = time.delta().as_secs_f32();
+    let now = time.elapsed_seconds();
+    let input_map = InputMap::default();
+    if player.bailed || !player.is_on_floor || grind.position.is_some() {
+        // Rolling off an edge, popping out or locking into a grind keeps it clean
+        manual.end(&mut ev_manual_ended, false);
+        manual.last_direction = None;
+        return;
+    }
+
+    // Back then forward lifts the nose, forward then back lifts the tail
+    for direction in [input_map.forward, input_map.back] {
+        if !key.just_pressed(direction) {
+            continue;
+        }
+        let combo = manual.last_direction
+            .filter(|(last_direction, pressed)| *last_direction != direction && now - pressed <= MANUAL_INPUT_WINDOW)
+            .map(|(last_direction, _pressed)| if last_direction == input_map.back {
+                ManualKind::Manual
+            } else {
+                ManualKind::NoseManual
+            });
+        manual.last_direction = Some((direction, now));
+        let Some(kind) = combo else {
+            continue;
+        };
+        manual.last_direction = None;
+        if manual.is_active() {
+            manual.end(&mut ev_manual_ended, false);
+            continue;
+        }
+        debug!(?kind, "Manual started");
+        manual.kind = Some(kind);
+        manual.balance = 0.0;
+        manual.balance_velocity = 0.0;
+        manual.duration = 0.0;
+    }
+    if !manual.is_active() {
+        return;
+    }
+
+    // Balance drifts and tips further the more it is off, forward and back push it around.
+    // Drift follows time in the manual rather than app time so replays play out the same.
+    manual.duration += delta;
+    let drift = (manual.duration * MANUAL_DRIFT_FREQUENCY).sin() * MANUAL_DRIFT_STRENGTH;
+    let mut balance_acceleration = manual.balance * MANUAL_BALANCE_INSTABILITY + drift - manual.balance_velocity * MANUAL_BALANCE_DAMPING;
+    if key.pressed(input_map.forward) {
+        balance_acceleration += MANUAL_BALANCE_CORRECTION;
+    }
+    if key.pressed(input_map.back) {
+        balance_acceleration -= MANUAL_BALANCE_CORRECTION;
+    }
+    manual.balance_velocity += balance_acceleration * delta;
+    manual.balance += manual.balance_velocity * delta;
+
+    if manual.balance.abs() > MANUAL_BALANCE_LIMIT {
+        manual.end(&mut ev_manual_ended, true);
+        ev_player_bail.send(PlayerBailEvent((player_entity, true)));
+    }
+}
+
+/// Pitches the body onto its wheels, on top of the lean set by `handle_player_movement`.
+pub fn apply_manual_pitch(
+    manual: Res<Manual>,
+    mut q_player_body: Query<&mut Transform, With<PlayerBody>>,
+) {
+    let Some(kind) = manual.kind else {
+        return;
+    };
+    for mut player_body_transform in q_player_body.iter_mut() {
+        player_body_transform.rotation *= Quat::from_rotation_x(kind.pitch() - manual.balance);
+    }
+}
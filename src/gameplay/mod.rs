@@ -0,0 +1,10 @@
+pub mod grind;
+pub mod hud;
+pub mod landing;
+pub mod manual;
+pub mod objectives;
+pub mod race;
+pub mod save;
+pub mod score;
+pub mod transition;
+pub mod tricks;
@@ -0,0 +1,261 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{entities::{collectibles::CollectedItems, player::player::Player}, levels::level::{CurrentLevel, Level}};
+
+use super::{grind::{GrindEndedEvent, GrindableKind}, manual::ManualEndedEvent, save::{level_save_path, read_save, write_save}, score::ComboEndedEvent};
+
+/// Kind of save file completed objectives are kept in.
+const OBJECTIVES_SAVE_KIND: &str = "objectives";
+
+/// What has to be done to complete an objective.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum ObjectiveGoal {
+    /// Reach a speed, in meters per second.
+    Speed { speed: f32 },
+    /// Bank points from combos.
+    Score { points: u32 },
+    /// Hold a single grind without bailing, optionally only on one kind of grindable, in seconds.
+    Grind {
+        duration: f32,
+        #[serde(default)]
+        kind: Option<GrindableKind>,
+    },
+    /// Hold a single manual without bailing, in seconds.
+    Manual { duration: f32 },
+    /// Pick up different collectibles on the level, counting ones picked up in earlier sessions.
+    Collect { count: u32 },
+}
+
+/// A challenge defined by the level.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LevelObjective {
+    pub name: String,
+    pub goal: ObjectiveGoal,
+    /// Seconds each attempt gets before its progress is thrown away, counted from its first progress.
+    /// Only score, grind and manual goals can be timed.
+    #[serde(default)]
+    pub time_limit: Option<f32>,
+}
+
+pub struct Objective {
+    pub name: String,
+    pub goal: ObjectiveGoal,
+    pub time_limit: Option<f32>,
+    pub progress: f32,
+    /// Time into the current attempt, started by its first progress when there is a time limit.
+    pub elapsed: Option<f32>,
+    pub completed: bool,
+}
+
+/// Objectives of the current level and how far along each one is.
+#[derive(Resource, Default)]
+pub struct Objectives {
+    pub objectives: Vec<Objective>,
+}
+
+/// Names of the completed objectives on a level, as saved to disk.
+#[derive(Serialize, Deserialize, Default)]
+pub struct ObjectivesSave {
+    pub completed: Vec<String>,
+}
+
+#[derive(Event)]
+pub struct ObjectiveCompletedEvent {
+    pub name: String,
+}
+
+impl ObjectiveGoal {
+    /// Progress at which the goal is met.
+    pub fn target(&self) -> f32 {
+        match *self {
+            ObjectiveGoal::Speed { speed } => speed,
+            ObjectiveGoal::Score { points } => points as f32,
+            ObjectiveGoal::Grind { duration, .. } => duration,
+            ObjectiveGoal::Manual { duration } => duration,
+            ObjectiveGoal::Collect { count } => count as f32,
+        }
+    }
+    /// Unit progress is shown in.
+    pub fn unit(&self) -> &'static str {
+        match self {
+            ObjectiveGoal::Speed { .. } => " m/s",
+            ObjectiveGoal::Score { .. } | ObjectiveGoal::Collect { .. } => "",
+            ObjectiveGoal::Grind { .. } | ObjectiveGoal::Manual { .. } => " s",
+        }
+    }
+    /// Whether a time limit applies. Speed is a single reading and collected items are kept for good,
+    /// so neither has progress to throw away.
+    pub fn can_be_timed(&self) -> bool {
+        match self {
+            ObjectiveGoal::Score { .. } | ObjectiveGoal::Grind { .. } | ObjectiveGoal::Manual { .. } => true,
+            ObjectiveGoal::Speed { .. } | ObjectiveGoal::Collect { .. } => false,
+        }
+    }
+    /// Decimal places progress is shown with.
+    pub fn precision(&self) -> usize {
+        match self {
+            ObjectiveGoal::Speed { .. } | ObjectiveGoal::Grind { .. } | ObjectiveGoal::Manual { .. } => 1,
+            ObjectiveGoal::Score { .. } | ObjectiveGoal::Collect { .. } => 0,
+        }
+    }
+}
+
+impl Objective {
+    /// Time left in the current attempt, if the objective is timed.
+    pub fn time_left(&self) -> Option<f32> {
+        self.time_limit.map(|time_limit| (time_limit - self.elapsed.unwrap_or(0.0)).max(0.0))
+    }
+}
+
+/// Rebuilds the objectives whenever the current level file loads or changes, keeping
+/// the ones completed in earlier sessions completed.
+pub fn load_objectives(
+    mut ev_level_asset: EventReader<AssetEvent<Level>>,
+    mut objectives: ResMut<Objectives>,
+    current_level: Res<CurrentLevel>,
+    levels: Res<Assets<Level>>,
+) {
+    let mut reload = false;
+    for ev in ev_level_asset.read() {
+        if ev.is_loaded_with_dependencies(&current_level.handle)
+            || ev.is_modified(&current_level.handle) {
+            reload = true;
+        }
+    }
+    if !reload {
+        return;
+    }
+    let Some(level) = levels.get(&current_level.handle) else {
+        return;
+    };
+    let objectives_save = level_save_path(&current_level, OBJECTIVES_SAVE_KIND)
+        .map(|path| read_save::<ObjectivesSave>(&path))
+        .transpose()
+        .unwrap_or_else(|error| {
+            warn!("Could not load completed objectives: {}", error);
+            None
+        })
+        .flatten()
+        .unwrap_or_default();
+
+    objectives.objectives = level.objectives.iter()
+        .map(|level_objective| {
+            let mut time_limit = level_objective.time_limit;
+            if time_limit.is_some() && !level_objective.goal.can_be_timed() {
+                warn!(name = %level_objective.name, goal = ?level_objective.goal, "Ignoring time limit on an objective that cannot be timed");
+                time_limit = None;
+            }
+            Objective {
+                name: level_objective.name.to_owned(),
+                goal: level_objective.goal.clone(),
+                time_limit,
+                progress: 0.0,
+                elapsed: None,
+                completed: objectives_save.completed.contains(&level_objective.name),
+            }
+        })
+        .collect();
+}
+
+/// Tracks objective progress from the player, the events of the scoring moves and the collected items.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_objectives(
+    mut ev_combo_ended: EventReader<ComboEndedEvent>,
+    mut ev_grind_ended: EventReader<GrindEndedEvent>,
+    mut ev_manual_ended: EventReader<ManualEndedEvent>,
+    mut ev_objective_completed: EventWriter<ObjectiveCompletedEvent>,
+    mut objectives: ResMut<Objectives>,
+    collected_items: Res<CollectedItems>,
+    current_level: Res<CurrentLevel>,
+    q_player: Query<&Player>,
+    time: Res<Time>,
+) {
+    // Every objective gets to see each event, so gather them up first
+    let banked_points: u32 = ev_combo_ended.read()
+        .filter(|ev| ev.banked)
+        .map(|ev| ev.points)
+        .sum();
+    let grinds: Vec<(GrindableKind, f32)> = ev_grind_ended.read()
+        .filter(|ev| !ev.bailed)
+        .map(|ev| (ev.kind, ev.duration))
+        .collect();
+    let manuals: Vec<f32> = ev_manual_ended.read()
+        .filter(|ev| !ev.bailed)
+        .map(|ev| ev.duration)
+        .collect();
+    let delta = time.delta().as_secs_f32();
+    let speed = q_player.get_single().map_or(0.0, |player| player.get_speed());
+
+    let mut completed_any = false;
+    for objective in objectives.objectives.iter_mut().filter(|objective| !objective.completed) {
+        let previous_progress = objective.progress;
+        match objective.goal {
+            ObjectiveGoal::Speed { .. } => {
+                objective.progress = objective.progress.max(speed);
+            },
+            ObjectiveGoal::Score { .. } => {
+                objective.progress += banked_points as f32;
+            },
+            ObjectiveGoal::Grind { kind, .. } => {
+                for (grind_kind, duration) in grinds.iter() {
+                    if kind.map_or(true, |kind| kind == *grind_kind) {
+                        objective.progress = objective.progress.max(*duration);
+                    }
+                }
+            },
+            ObjectiveGoal::Manual { .. } => {
+                for duration in manuals.iter() {
+                    objective.progress = objective.progress.max(*duration);
+                }
+            },
+            ObjectiveGoal::Collect { .. } => {
+                // Collected items are saved, so one-off pickups from earlier sessions still count
+                objective.progress = collected_items.collected.len() as f32;
+            },
+        }
+
+        if objective.progress >= objective.goal.target() {
+            info!(name = %objective.name, "Objective completed");
+            objective.completed = true;
+            completed_any = true;
+            ev_objective_completed.send(ObjectiveCompletedEvent {
+                name: objective.name.to_owned(),
+            });
+            continue;
+        }
+        // An attempt starts with its first progress, and running out of time waits for the next one
+        let Some(time_limit) = objective.time_limit else {
+            continue;
+        };
+        let Some(elapsed) = objective.elapsed else {
+            if objective.progress > previous_progress {
+                objective.elapsed = Some(0.0);
+            }
+            continue;
+        };
+        if elapsed + delta > time_limit {
+            debug!(name = %objective.name, progress = objective.progress, "Objective attempt timed out");
+            objective.elapsed = None;
+            objective.progress = 0.0;
+        } else {
+            objective.elapsed = Some(elapsed + delta);
+        }
+    }
+    if !completed_any {
+        return;
+    }
+
+    let Some(path) = level_save_path(&current_level, OBJECTIVES_SAVE_KIND) else {
+        return;
+    };
+    let objectives_save = ObjectivesSave {
+        completed: objectives.objectives.iter()
+            .filter(|objective| objective.completed)
+            .map(|objective| objective.name.to_owned())
+            .collect(),
+    };
+    if let Err(error) = write_save(&path, &objectives_save) {
+        warn!("Could not save completed objectives: {}", error);
+    }
+}
@@ -0,0 +1,270 @@
+use avian3d::prelude::CollisionStarted;
+use bevy::{pbr::NotShadowCaster, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{entities::player::player::PlayerBody, levels::level::CurrentLevel};
+
+use super::save::{level_save_path, read_save, write_save};
+
+/// Kind of save file the personal best is kept in.
+const RACE_SAVE_KIND: &str = "race";
+/// Time between recorded ghost frames, in seconds.
+const GHOST_FRAME_INTERVAL: f32 = 1.0 / 30.0;
+const GHOST_COLOR: Color = Color::srgba(0.6, 0.9, 1.0, 0.35);
+
+/// Trigger volume the player has to pass through in order, the first one starting the timer
+/// and the last one finishing the run.
+#[derive(Component)]
+pub struct RaceGate {
+    pub index: usize,
+}
+
+/// Translucent stand-in for the player that replays the personal best.
+#[derive(Component)]
+pub struct RaceGhost;
+
+/// Player body transform at a point in a run.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct GhostFrame {
+    pub time: f32,
+    pub location: Vec3,
+    pub rotation: Quat,
+}
+
+/// A finished run, saved to disk when it is the fastest one on the level.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RaceRecord {
+    pub time: f32,
+    /// Time at which each gate after the first was passed.
+    pub splits: Vec<f32>,
+    pub frames: Vec<GhostFrame>,
+}
+
+pub struct RaceRun {
+    pub elapsed: f32,
+    pub next_gate: usize,
+    pub splits: Vec<f32>,
+    frames: Vec<GhostFrame>,
+}
+
+#[derive(Resource, Default)]
+pub struct Race {
+    pub run: Option<RaceRun>,
+    pub personal_best: Option<RaceRecord>,
+    /// Time of the last finished run, kept until the next one starts.
+    pub last_time: Option<f32>,
+}
+
+#[derive(Event)]
+pub struct RaceSplitEvent {
+    pub gate: usize,
+    pub time: f32,
+    /// Difference to the personal best at the same gate, negative when ahead.
+    pub difference: Option<f32>,
+}
+
+#[derive(Event)]
+pub struct RaceFinishedEvent {
+    pub time: f32,
+    pub personal_best: bool,
+}
+
+impl RaceRecord {
+    /// Ghost transform `time` into the run, blending between the recorded frames.
+    pub fn sample(&self, time: f32) -> Option<GhostFrame> {
+        let next = self.frames.partition_point(|frame| frame.time < time);
+        let after = *self.frames.get(next)?;
+        let Some(before) = next.checked_sub(1).map(|previous| self.frames[previous]) else {
+            return Some(after);
+        };
+        let t = ((time - before.time) / (after.time - before.time).max(f32::EPSILON)).clamp(0.0, 1.0);
+        Some(GhostFrame {
+            time,
+            location: before.location.lerp(after.location, t),
+            rotation: before.rotation.slerp(after.rotation, t),
+        })
+    }
+}
+
+/// Formats a run time as minutes, seconds and hundredths.
+pub fn format_race_time(time: f32) -> String {
+    let minutes = (time / 60.0).floor();
+    format!("{}:{:05.2}", minutes, time - minutes * 60.0)
+}
+
+pub fn spawn_race_ghost(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        RaceGhost,
+        Name::new("Race Ghost"),
+        NotShadowCaster,
+        PbrBundle {
+            mesh: meshes.add(Capsule3d::new(0.5, 1.0)),
+            material: materials.add(StandardMaterial {
+                base_color: GHOST_COLOR,
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            }),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+    ));
+}
+
+/// Drops the running race and loads the personal best whenever the level changes.
+pub fn load_personal_best(
+    mut race: ResMut<Race>,
+    current_level: Res<CurrentLevel>,
+) {
+    *race = Race::default();
+    let Some(path) = level_save_path(&current_level, RACE_SAVE_KIND) else {
+        return;
+    };
+    match read_save::<RaceRecord>(&path) {
+        Ok(personal_best) => race.personal_best = personal_best,
+        Err(error) => warn!("Could not load personal best: {}", error),
+    }
+}
+
+/// Throws away the running race while noclip, the editor or the console can move the player,
+/// so a run cut short that way is never saved as the personal best.
+pub fn cancel_race_run(mut race: ResMut<Race>) {
+    if race.run.take().is_some() {
+        info!("Race cancelled");
+    }
+}
+
+/// Advances the timer and records the ghost of the running race.
+pub fn update_race_timer(
+    mut race: ResMut<Race>,
+    q_player_body: Query<&GlobalTransform, With<PlayerBody>>,
+    time: Res<Time>,
+) {
+    let Some(run) = race.run.as_mut() else {
+        return;
+    };
+    run.elapsed += time.delta().as_secs_f32();
+    let Ok(player_body_global_transform) = q_player_body.get_single() else {
+        return;
+    };
+    if run.frames.last().is_some_and(|frame| run.elapsed - frame.time < GHOST_FRAME_INTERVAL) {
+        return;
+    }
+    let (_scale, rotation, location) = player_body_global_transform.to_scale_rotation_translation();
+    run.frames.push(GhostFrame {
+        time: run.elapsed,
+        location,
+        rotation,
+    });
+}
+
+pub fn handle_race_gates(
+    mut ev_collision_started: EventReader<CollisionStarted>,
+    mut ev_race_split: EventWriter<RaceSplitEvent>,
+    mut ev_race_finished: EventWriter<RaceFinishedEvent>,
+    mut race: ResMut<Race>,
+    current_level: Res<CurrentLevel>,
+    q_player_body: Query<(), With<PlayerBody>>,
+    q_race_gates: Query<&RaceGate>,
+) {
+    for CollisionStarted(entity1, entity2) in ev_collision_started.read() {
+        let gate_entity = if q_player_body.contains(*entity1) {
+            *entity2
+        } else if q_player_body.contains(*entity2) {
+            *entity1
+        } else {
+            continue;
+        };
+        let Ok(race_gate) = q_race_gates.get(gate_entity) else {
+            continue;
+        };
+        let gate_count = q_race_gates.iter().count();
+
+        // The first gate always (re)starts the run
+        if race_gate.index == 0 {
+            info!("Race started");
+            race.last_time = None;
+            race.run = Some(RaceRun {
+                elapsed: 0.0,
+                next_gate: 1,
+                splits: Vec::new(),
+                frames: Vec::new(),
+            });
+            continue;
+        }
+        let personal_best_split = race.personal_best.as_ref()
+            .and_then(|personal_best| personal_best.splits.get(race_gate.index - 1).copied());
+        let Some(run) = race.run.as_mut() else {
+            continue;
+        };
+        // Gates passed out of order or twice do not count
+        if race_gate.index != run.next_gate {
+            continue;
+        }
+        run.splits.push(run.elapsed);
+        run.next_gate += 1;
+        let difference = personal_best_split.map(|split| run.elapsed - split);
+        debug!(gate = race_gate.index, time = run.elapsed, ?difference, "Race split");
+        ev_race_split.send(RaceSplitEvent {
+            gate: race_gate.index,
+            time: run.elapsed,
+            difference,
+        });
+        if run.next_gate < gate_count {
+            continue;
+        }
+
+        // Passed the last gate
+        let Some(run) = race.run.take() else {
+            continue;
+        };
+        let record = RaceRecord {
+            time: run.elapsed,
+            splits: run.splits,
+            frames: run.frames,
+        };
+        let personal_best = race.personal_best.as_ref()
+            .map_or(true, |personal_best| record.time < personal_best.time);
+        info!(time = record.time, personal_best, "Race finished");
+        race.last_time = Some(record.time);
+        ev_race_finished.send(RaceFinishedEvent {
+            time: record.time,
+            personal_best,
+        });
+        if !personal_best {
+            continue;
+        }
+        if let Some(path) = level_save_path(&current_level, RACE_SAVE_KIND) {
+            if let Err(error) = write_save(&path, &record) {
+                warn!("Could not save personal best: {}", error);
+            }
+        }
+        race.personal_best = Some(record);
+    }
+}
+
+/// Moves the ghost along the personal best in step with the running race.
+pub fn update_race_ghost(
+    race: Res<Race>,
+    mut q_race_ghost: Query<(&mut Transform, &mut Visibility), With<RaceGhost>>,
+) {
+    let ghost_frame = race.run.as_ref()
+        .zip(race.personal_best.as_ref())
+        .and_then(|(run, personal_best)| personal_best.sample(run.elapsed));
+    for (mut transform, mut visibility) in q_race_ghost.iter_mut() {
+        let Some(ghost_frame) = ghost_frame else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        *visibility = Visibility::Inherited;
+        *transform = Transform {
+            translation: ghost_frame.location,
+            rotation: ghost_frame.rotation,
+            ..default()
+        };
+    }
+}
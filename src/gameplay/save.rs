@@ -0,0 +1,40 @@
+use std::{fs, io::ErrorKind, path::{Path, PathBuf}};
+
+use ron::ser::PrettyConfig;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::levels::level::CurrentLevel;
+
+/// Directory per level progress is kept in, next to the working directory.
+pub const SAVE_DIR: &str = "saves";
+
+/// Save file of `kind` for the current level, e.g. `saves/default.race.ron`.
+pub fn level_save_path(current_level: &CurrentLevel, kind: &str) -> Option<PathBuf> {
+    let level_path = current_level.handle.path()?;
+    let file_name = level_path.path().file_name()?.to_str()?;
+    let level_name = file_name.strip_suffix(".level.ron").unwrap_or(file_name);
+    Some(PathBuf::from(SAVE_DIR).join(format!("{}.{}.ron", level_name, kind)))
+}
+
+/// Reads a save file, with `None` when nothing has been saved yet.
+pub fn read_save<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, String> {
+    let serialized_save = match fs::read_to_string(path) {
+        Ok(serialized_save) => serialized_save,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(format!("could not read '{}': {}", path.display(), error)),
+    };
+    ron::from_str(&serialized_save)
+        .map(Some)
+        .map_err(|error| format!("could not parse '{}': {}", path.display(), error))
+}
+
+pub fn write_save<T: Serialize>(path: &Path, save: &T) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|error| format!("could not create '{}': {}", parent.display(), error))?;
+    }
+    let serialized_save = ron::ser::to_string_pretty(save, PrettyConfig::default())
+        .map_err(|error| format!("could not serialize save: {}", error))?;
+    fs::write(path, serialized_save)
+        .map_err(|error| format!("could not write '{}': {}", path.display(), error))
+}
@@ -0,0 +1,186 @@
+use avian3d::math::PI;
+use bevy::prelude::*;
+
+use crate::{entities::player::player::{Player, PlayerBailEvent}, utils::math::wrap_angle};
+
+use super::{grind::GrindEndedEvent, manual::{Manual, ManualEndedEvent}, tricks::{PlayerAirtime, PlayerLandedEvent, TrickLandedEvent}};
+
+const AIRTIME_POINTS_PER_SECOND: f32 = 100.0;
+const MIN_SCORED_AIRTIME: f32 = 0.3;
+const SPIN_POINTS_PER_HALF_TURN: u32 = 180;
+/// Yaw short of a half turn that still counts as one, in radians.
+const SPIN_TOLERANCE: f32 = 0.35;
+const GRIND_POINTS_PER_SECOND: f32 = 150.0;
+const MANUAL_POINTS_PER_SECOND: f32 = 100.0;
+const SPEED_POINTS_PER_SECOND: f32 = 2.0;
+/// Speed above which going fast scores, in meters per second.
+const MIN_SCORED_SPEED: f32 = 6.0;
+const DISTANCE_POINTS_PER_METER: f32 = 1.0;
+/// Below this speed on the floor, in meters per second, the combo starts running out.
+const COMBO_MIN_SPEED: f32 = 1.0;
+const COMBO_TIMEOUT: f32 = 1.5;
+const MAX_COMBO_MULTIPLIER: u32 = 10;
+
+#[derive(Resource)]
+pub struct Score {
+    pub total: u32,
+    /// Points in the running combo, banked times the multiplier once it ends.
+    pub combo_points: f32,
+    pub combo_multiplier: u32,
+    pub combo_timer: f32,
+    /// Yaw turned during the current air, in radians.
+    pub air_yaw: f32,
+    last_yaw: f32,
+    last_location: Option<Vec3>,
+}
+
+#[derive(Event)]
+pub struct ComboEndedEvent {
+    pub points: u32,
+    pub banked: bool,
+}
+
+impl Default for Score {
+    fn default() -> Self {
+        Self {
+            total: 0,
+            combo_points: 0.0,
+            combo_multiplier: 1,
+            combo_timer: 0.0,
+            air_yaw: 0.0,
+            last_yaw: 0.0,
+            last_location: None,
+        }
+    }
+}
+
+impl Score {
+    pub fn in_combo(&self) -> bool {
+        self.combo_points > 0.0
+    }
+    /// Points the current combo is worth if it ended now.
+    pub fn combo_value(&self) -> u32 {
+        (self.combo_points * self.combo_multiplier as f32) as u32
+    }
+    /// Adds a scoring move to the combo, bumping the multiplier.
+    pub fn add_move(&mut self, points: f32) {
+        if self.in_combo() {
+            self.combo_multiplier = (self.combo_multiplier + 1).min(MAX_COMBO_MULTIPLIER);
+        }
+        self.combo_points += points;
+        self.combo_timer = 0.0;
+    }
+    fn end_combo(&mut self, banked: bool) -> u32 {
+        let points = self.combo_value();
+        if banked {
+            self.total += points;
+        }
+        self.combo_points = 0.0;
+        self.combo_multiplier = 1;
+        self.combo_timer = 0.0;
+        points
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_score(
+    mut ev_player_landed: EventReader<PlayerLandedEvent>,
+    mut ev_trick_landed: EventReader<TrickLandedEvent>,
+    mut ev_grind_ended: EventReader<GrindEndedEvent>,
+    mut ev_manual_ended: EventReader<ManualEndedEvent>,
+    mut ev_combo_ended: EventWriter<ComboEndedEvent>,
+    mut score: ResMut<Score>,
+    player_airtime: Res<PlayerAirtime>,
+    manual: Res<Manual>,
+    q_player: Query<&Player>,
+    time: Res<Time>,
+) {
+    let Ok(player) = q_player.get_single() else {
+        return;
+    };
+    let delta = time.delta().as_secs_f32();
+    if delta <= 0.0 || player.bailed {
+        score.last_location = None;
+        return;
+    }
+
+    // Measured over the velocity timestep, so the same run scores the same at any frame rate
+    let speed = player.get_speed();
+    let ground_location = player.get_location() * Vec3::new(1.0, 0.0, 1.0);
+    let yaw = player.get_rotation().y;
+
+    if player_airtime.airborne {
+        score.air_yaw += wrap_angle(yaw - score.last_yaw);
+    }
+    if score.in_combo() {
+        if let Some(last_location) = score.last_location.filter(|_| !player_airtime.airborne) {
+            score.combo_points += last_location.distance(ground_location) * DISTANCE_POINTS_PER_METER;
+        }
+        if speed > MIN_SCORED_SPEED {
+            score.combo_points += SPEED_POINTS_PER_SECOND * speed * delta;
+        }
+    }
+    score.last_yaw = yaw;
+    score.last_location = Some(ground_location);
+
+    for ev in ev_player_landed.read() {
+        if ev.airtime >= MIN_SCORED_AIRTIME {
+            score.add_move(ev.airtime * AIRTIME_POINTS_PER_SECOND);
+        }
+        let half_turns = ((score.air_yaw.abs() + SPIN_TOLERANCE) / PI).floor() as u32;
+        if half_turns > 0 {
+            debug!(degrees = half_turns * 180, "Spin landed");
+            score.add_move((half_turns * SPIN_POINTS_PER_HALF_TURN) as f32);
+        }
+        score.air_yaw = 0.0;
+    }
+    for ev in ev_trick_landed.read() {
+        score.add_move(ev.points as f32);
+    }
+    for ev in ev_grind_ended.read().filter(|ev| !ev.bailed) {
+        score.add_move(ev.duration * GRIND_POINTS_PER_SECOND);
+    }
+    for ev in ev_manual_ended.read().filter(|ev| !ev.bailed) {
+        score.add_move(ev.duration * MANUAL_POINTS_PER_SECOND);
+    }
+
+    // The combo holds while the player keeps moving, is in the air or is manualling between moves
+    if !score.in_combo() {
+        return;
+    }
+    if player_airtime.airborne || manual.is_active() || speed > COMBO_MIN_SPEED {
+        score.combo_timer = 0.0;
+    } else {
+        score.combo_timer += delta;
+    }
+    if score.combo_timer > COMBO_TIMEOUT {
+        let points = score.end_combo(true);
+        info!(points, total = score.total, "Combo banked");
+        ev_combo_ended.send(ComboEndedEvent {
+            points,
+            banked: true,
+        });
+    }
+}
+
+pub fn handle_score_bail(
+    mut ev_player_bail: EventReader<PlayerBailEvent>,
+    mut ev_combo_ended: EventWriter<ComboEndedEvent>,
+    mut score: ResMut<Score>,
+) {
+    for ev in ev_player_bail.read() {
+        let (_player_entity, bailed) = ev.0;
+        if !bailed {
+            continue;
+        }
+        score.air_yaw = 0.0;
+        if score.in_combo() {
+            let points = score.end_combo(false);
+            info!(points, "Combo lost");
+            ev_combo_ended.send(ComboEndedEvent {
+                points,
+                banked: false,
+            });
+        }
+    }
+}
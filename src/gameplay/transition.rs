@@ -0,0 +1,263 @@
+use avian3d::math::TAU;
+use bevy::prelude::*;
+
+use crate::{controls::{controls::InputMap, player::PlayerTuning}, entities::player::player::{Player, PlayerBody, BODY_OFFSET_VEC3, VELOCITY_TIMESTEP}, utils::math::wrap_angle};
+
+/// How far from the feet the riding surface can be and still be snapped onto.
+const TRANSITION_SNAP_DISTANCE: f32 = 0.5;
+const TRANSITION_SNAP_COOLDOWN: f32 = 0.1;
+/// Surfaces flatter than this are ridden with the normal movement instead.
+const TRANSITION_MIN_SLOPE: f32 = 0.05;
+const TRANSITION_FRICTION: f32 = 0.2;
+/// Below this speed on a flat part of the surface, in meters per second, the ride hands back to normal movement.
+const TRANSITION_MIN_SPEED: f32 = 1.0;
+/// How quickly the player turns to face the way they are rolling, in radians per second.
+const TRANSITION_REORIENT_SPEED: f32 = 8.0;
+/// Distance the player is pushed off the surface when launching, so the deck is not caught.
+const TRANSITION_LIP_CLEARANCE: f32 = 0.05;
+
+/// The rideable surface of a ramp or pipe, extruded across local X.
+#[derive(Component, Clone)]
+pub struct Transition {
+    /// Local (z, y) points from one end of the surface to the other, with increasing z.
+    pub profile: Vec<Vec2>,
+    pub width: f32,
+}
+
+#[derive(Resource, Default)]
+pub struct TransitionRide {
+    pub transition: Option<Entity>,
+    /// Distance along the profile from its first point, in world units once scaled.
+    pub distance: f32,
+    /// Offset across the transition from its centre line.
+    pub lateral: f32,
+    /// Speed along the profile, an offset per frame like `Player` velocity.
+    pub speed: f32,
+    /// Speed across the transition, an offset per frame like `Player` velocity.
+    pub lateral_speed: f32,
+    yaw: f32,
+    cooldown: f32,
+}
+
+impl Transition {
+    /// The transition at the size it is in the world, so distances along and across it are in world units.
+    pub fn scaled(&self, scale: Vec3) -> Self {
+        Self {
+            profile: self.profile.iter()
+                .map(|point| Vec2::new(point.x * scale.z, point.y * scale.y))
+                .collect(),
+            width: self.width * scale.x,
+        }
+    }
+    pub fn length(&self) -> f32 {
+        self.profile.windows(2).map(|segment| segment[0].distance(segment[1])).sum()
+    }
+    /// Point and unit tangent on the profile `distance` along it, clamped to its ends.
+    pub fn sample(&self, distance: f32) -> (Vec2, Vec2) {
+        let mut remaining = distance.max(0.0);
+        let mut last = (self.profile.first().copied().unwrap_or_default(), Vec2::X);
+        for segment in self.profile.windows(2) {
+            let (start, end) = (segment[0], segment[1]);
+            let length = start.distance(end);
+            let tangent = (end - start).normalize_or_zero();
+            if remaining <= length {
+                return (start + tangent * remaining, tangent);
+            }
+            remaining -= length;
+            last = (end, tangent);
+        }
+        last
+    }
+    /// Closest point on the profile to a local (z, y) point, as (distance along it, distance to it).
+    pub fn closest(&self, point: Vec2) -> Option<(f32, f32)> {
+        let mut travelled = 0.0;
+        self.profile.windows(2)
+            .map(|segment| {
+                let (start, end) = (segment[0], segment[1]);
+                let length = start.distance(end);
+                let along = (point - start).dot((end - start).normalize_or_zero()).clamp(0.0, length);
+                let offset = point.distance(start + (end - start).normalize_or_zero() * along);
+                let closest = (travelled + along, offset);
+                travelled += length;
+                closest
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+}
+
+/// Profile (z, y) directions to local space.
+fn profile_to_local(direction: Vec2) -> Vec3 {
+    Vec3::new(0.0, direction.y, direction.x)
+}
+
+/// Surface normal for a profile tangent, pointing out of the solid since z always increases.
+fn profile_normal(tangent: Vec2) -> Vec3 {
+    Vec3::new(0.0, tangent.x, -tangent.y)
+}
+
+pub fn player_riding_transition(transition_ride: Res<TransitionRide>) -> bool {
+    transition_ride.transition.is_some()
+}
+
+pub fn handle_transition_entry(
+    mut transition_ride: ResMut<TransitionRide>,
+    mut q_player: Query<&mut Player>,
+    q_transitions: Query<(Entity, &Transition, &GlobalTransform)>,
+    time: Res<Time>,
+) {
+    transition_ride.cooldown = (transition_ride.cooldown - time.delta().as_secs_f32()).max(0.0);
+    let Ok(mut player) = q_player.get_single_mut() else {
+        return;
+    };
+    let velocity = player.get_velocity();
+    if transition_ride.transition.is_some() || transition_ride.cooldown > 0.0 || player.bailed
+        || (!player.is_on_floor && velocity.y > 0.0) {
+        return;
+    }
+    let location = player.get_location();
+
+    // Scale is applied to the profile so the ride itself happens in world units
+    let candidate = q_transitions.iter()
+        .filter_map(|(entity, transition, global_transform)| {
+            let transform = global_transform.compute_transform();
+            let transition = transition.scaled(transform.scale);
+            let local = transform.rotation.inverse() * (location - transform.translation);
+            if local.x.abs() > transition.width / 2.0 {
+                return None;
+            }
+            let (distance, offset) = transition.closest(Vec2::new(local.z, local.y))?;
+            Some((entity, transition, transform.rotation, local.x, distance, offset))
+        })
+        .filter(|(_entity, _transition, _rotation, _lateral, _distance, offset)| *offset < TRANSITION_SNAP_DISTANCE)
+        .min_by(|a, b| a.5.total_cmp(&b.5));
+    let Some((entity, transition, rotation, lateral, distance, _offset)) = candidate else {
+        return;
+    };
+    let (_point, tangent) = transition.sample(distance);
+    if tangent.y.abs() < TRANSITION_MIN_SLOPE {
+        return;
+    }
+
+    // Carry over whatever part of the velocity runs along the surface
+    let tangent_world = rotation * profile_to_local(tangent);
+    let across_world = rotation * Vec3::X;
+    transition_ride.transition = Some(entity);
+    transition_ride.distance = distance;
+    transition_ride.lateral = lateral;
+    transition_ride.speed = velocity.dot(tangent_world);
+    transition_ride.lateral_speed = velocity.dot(across_world);
+    transition_ride.yaw = player.get_rotation().y;
+    player.is_on_floor = true;
+    debug!(?entity, distance, "Riding transition");
+}
+
+/// Rolls the player along the transition profile, launching them off the ends.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_transition_ride(
+    key: Res<ButtonInput<KeyCode>>,
+    mut transition_ride: ResMut<TransitionRide>,
+    player_tuning: Res<PlayerTuning>,
+    mut q_player: Query<(&mut Player, &mut Transform), Without<PlayerBody>>,
+    mut q_player_body: Query<(&mut PlayerBody, &mut Transform), Without<Player>>,
+    q_transitions: Query<(&Transition, &GlobalTransform)>,
+    time: Res<Time>,
+) {
+    let Some(entity) = transition_ride.transition else {
+        return;
+    };
+    let (Ok((mut player, mut player_transform)), Ok((mut player_body, mut player_body_transform))) = (q_player.get_single_mut(), q_player_body.get_single_mut()) else {
+        return;
+    };
+    let Ok((transition, global_transform)) = q_transitions.get(entity) else {
+        transition_ride.transition = None;
+        return;
+    };
+    if player.bailed {
+        transition_ride.transition = None;
+        transition_ride.cooldown = TRANSITION_SNAP_COOLDOWN;
+        return;
+    }
+    let delta = time.delta().as_secs_f32();
+    let input_map = InputMap::default();
+    let transform = global_transform.compute_transform();
+    let transition = transition.scaled(transform.scale);
+    let rotation = transform.rotation;
+
+    // Gravity pulls along the surface the same way it pulls the player in the air
+    let (_point, tangent) = transition.sample(transition_ride.distance);
+    let tangent_world = rotation * profile_to_local(tangent);
+    transition_ride.speed += delta * player_tuning.acceleration * player_tuning.gravity * tangent_world.y;
+    transition_ride.speed *= 1.0 - TRANSITION_FRICTION * delta;
+    transition_ride.lateral_speed *= 1.0 - TRANSITION_FRICTION * delta;
+
+    // Carve by turning the heading across the surface
+    let mut lean = player_body.lean.lerp(0.0, player_tuning.lean_speed * delta);
+    let mut turn = 0.0;
+    if key.pressed(input_map.left) {
+        turn += player_tuning.turn_speed * TAU * delta;
+        lean = player_body.lean.lerp(player_tuning.lean_max_angle, player_tuning.lean_speed * delta);
+    } else if key.pressed(input_map.right) {
+        turn -= player_tuning.turn_speed * TAU * delta;
+        lean = player_body.lean.lerp(-player_tuning.lean_max_angle, player_tuning.lean_speed * delta);
+    }
+    let heading = Vec2::from_angle(turn).rotate(Vec2::new(transition_ride.speed, transition_ride.lateral_speed));
+    transition_ride.speed = heading.x;
+    transition_ride.lateral_speed = heading.y;
+    transition_ride.distance += transition_ride.speed * delta / VELOCITY_TIMESTEP;
+    transition_ride.lateral += transition_ride.lateral_speed * delta / VELOCITY_TIMESTEP;
+
+    let length = transition.length();
+    let off_end = transition_ride.distance < 0.0 || transition_ride.distance > length;
+    let off_side = transition_ride.lateral.abs() > transition.width / 2.0;
+    transition_ride.distance = transition_ride.distance.clamp(0.0, length);
+
+    // Place the player on the surface, standing along its normal
+    let (point, tangent) = transition.sample(transition_ride.distance);
+    let tangent_world = rotation * profile_to_local(tangent);
+    let across_world = rotation * Vec3::X;
+    let normal_world = rotation * profile_normal(tangent);
+    let location = transform.translation + rotation * Vec3::new(transition_ride.lateral, point.y, point.x);
+    let velocity = tangent_world * transition_ride.speed + across_world * transition_ride.lateral_speed;
+
+    // Turn towards the direction of travel, which swings the player back round after a vert air
+    let heading_world = velocity * Vec3::new(1.0, 0.0, 1.0);
+    if heading_world.length_squared() > f32::EPSILON {
+        let target_yaw = f32::atan2(-heading_world.x, -heading_world.z);
+        let max_turn = TRANSITION_REORIENT_SPEED * delta;
+        transition_ride.yaw = (transition_ride.yaw + wrap_angle(target_yaw - transition_ride.yaw).clamp(-max_turn, max_turn)) % TAU;
+    }
+
+    player.set_rotation(Vec3::new(0.0, transition_ride.yaw, 0.0));
+    player.set_location(location);
+    player.set_velocity(velocity);
+    player_body.lean = lean;
+    *player_transform = Transform {
+        translation: location,
+        rotation: Quat::from_rotation_arc(Vec3::Y, normal_world) * Quat::from_rotation_y(transition_ride.yaw),
+        ..default()
+    };
+    *player_body_transform = Transform {
+        translation: BODY_OFFSET_VEC3,
+        rotation: Quat::from_rotation_z(lean),
+        ..default()
+    };
+
+    let flat = tangent_world.y.abs() < TRANSITION_MIN_SLOPE;
+    let jumped = key.just_pressed(input_map.jump);
+    let stopped = flat && velocity.length() < TRANSITION_MIN_SPEED * VELOCITY_TIMESTEP;
+    if !(jumped || off_end || off_side || stopped) {
+        return;
+    }
+
+    // Leave the transition, launching off the lip unless rolling out onto flat ground
+    if jumped {
+        player.set_velocity(velocity + normal_world * player_tuning.jump_velocity);
+    }
+    player.is_on_floor = flat && !jumped && !off_side;
+    if !player.is_on_floor {
+        player.set_location(location + normal_world * TRANSITION_LIP_CLEARANCE);
+        debug!(velocity = %player.get_velocity(), "Launched off transition");
+    }
+    transition_ride.transition = None;
+    transition_ride.cooldown = TRANSITION_SNAP_COOLDOWN;
+}
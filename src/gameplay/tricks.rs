@@ -0,0 +1,288 @@
+use bevy::{asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext}, prelude::*};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{controls::controls::InputMap, entities::player::player::{Player, PlayerBailEvent, PlayerBody}, states::loading::GameAssets};
+
+pub const TRICK_TABLE_PATH: &str = "tricks/default.tricks.ron";
+
+/// How long a direction press counts towards the next trick button.
+const TRICK_INPUT_WINDOW: f32 = 0.4;
+const TRICK_INPUT_BUFFER_LENGTH: usize = 4;
+/// Rotation still left on a trick when landing that is forgiven, in radians.
+const TRICK_LANDING_TOLERANCE: f32 = 0.35;
+/// Fraction of a trick still left when landing that is forgiven.
+const TRICK_LANDING_WINDOW: f32 = 0.25;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum TrickInput {
+    Forward,
+    Back,
+    Left,
+    Right,
+    Flip,
+    Grab,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Trick {
+    pub name: String,
+    /// Directions and buttons pressed in order, always ending in a button.
+    pub inputs: Vec<TrickInput>,
+    /// Total body rotation over the trick, as euler XYZ radians.
+    #[serde(default)]
+    pub rotation: Vec3,
+    pub duration: f32,
+    pub points: u32,
+}
+
+#[derive(Asset, TypePath, Serialize, Deserialize, Clone, Default)]
+pub struct TrickTable {
+    pub tricks: Vec<Trick>,
+}
+
+#[derive(Default)]
+pub struct TrickTableLoader;
+
+#[derive(Debug, Error)]
+pub enum TrickTableLoaderError {
+    #[error("Could not read trick table: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse trick table: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+/// Time spent off the floor, tracked from `Player::is_on_floor` changes.
+#[derive(Resource, Default)]
+pub struct PlayerAirtime {
+    pub airborne: bool,
+    pub airtime: f32,
+}
+
+#[derive(Event)]
+pub struct PlayerTakeoffEvent;
+
+#[derive(Event)]
+pub struct PlayerLandedEvent {
+    pub airtime: f32,
+}
+
+#[derive(Event)]
+pub struct TrickLandedEvent {
+    pub name: String,
+    pub points: u32,
+}
+
+#[derive(Event)]
+pub struct TrickFailedEvent {
+    pub name: String,
+}
+
+pub struct ActiveTrick {
+    pub trick: Trick,
+    pub elapsed: f32,
+}
+
+#[derive(Resource, Default)]
+pub struct TrickState {
+    pub inputs: Vec<(TrickInput, f32)>,
+    pub active: Option<ActiveTrick>,
+    pub completed: Vec<Trick>,
+}
+
+impl AssetLoader for TrickTableLoader {
+    type Asset = TrickTable;
+    type Settings = ();
+    type Error = TrickTableLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let trick_table = ron::de::from_bytes::<TrickTable>(&bytes)?;
+        Ok(trick_table)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tricks.ron"]
+    }
+}
+
+impl TrickTable {
+    /// The trick with the longest input sequence ending the buffered inputs.
+    pub fn find(&self, inputs: &[TrickInput]) -> Option<&Trick> {
+        self.tricks.iter()
+            .filter(|trick| !trick.inputs.is_empty() && inputs.ends_with(&trick.inputs))
+            .max_by_key(|trick| trick.inputs.len())
+    }
+}
+
+impl ActiveTrick {
+    pub fn progress(&self) -> f32 {
+        if self.trick.duration <= 0.0 {
+            return 1.0;
+        }
+        (self.elapsed / self.trick.duration).min(1.0)
+    }
+    pub fn rotation(&self) -> Quat {
+        let rotation = self.trick.rotation * self.progress();
+        Quat::from_euler(EulerRot::XYZ, rotation.x, rotation.y, rotation.z)
+    }
+    /// Whether the board would come down clean if the player touched the floor now.
+    pub fn is_landable(&self) -> bool {
+        let remaining = 1.0 - self.progress();
+        remaining <= TRICK_LANDING_WINDOW
+            && remaining * self.trick.rotation.length() <= TRICK_LANDING_TOLERANCE
+    }
+}
+
+impl TrickState {
+    fn reset(&mut self) {
+        self.inputs.clear();
+        self.active = None;
+        self.completed.clear();
+    }
+}
+
+pub fn handle_player_airtime(
+    mut ev_player_takeoff: EventWriter<PlayerTakeoffEvent>,
+    mut ev_player_landed: EventWriter<PlayerLandedEvent>,
+    mut player_airtime: ResMut<PlayerAirtime>,
+    q_player: Query<&Player>,
+    time: Res<Time>,
+) {
+    let Ok(player) = q_player.get_single() else {
+        return;
+    };
+    let airborne = !player.is_on_floor && !player.bailed;
+    if airborne {
+        if !player_airtime.airborne {
+            player_airtime.airtime = 0.0;
+            ev_player_takeoff.send(PlayerTakeoffEvent);
+        }
+        player_airtime.airtime += time.delta().as_secs_f32();
+    } else if player_airtime.airborne && player.is_on_floor {
+        ev_player_landed.send(PlayerLandedEvent {
+            airtime: player_airtime.airtime,
+        });
+    }
+    player_airtime.airborne = airborne;
+}
+
+pub fn handle_trick_input(
+    key: Res<ButtonInput<KeyCode>>,
+    player_airtime: Res<PlayerAirtime>,
+    game_assets: Res<GameAssets>,
+    trick_tables: Res<Assets<TrickTable>>,
+    mut trick_state: ResMut<TrickState>,
+    time: Res<Time>,
+) {
+    if !player_airtime.airborne {
+        trick_state.inputs.clear();
+        return;
+    }
+    let Some(trick_table) = trick_tables.get(&game_assets.tricks) else {
+        return;
+    };
+    let input_map = InputMap::default();
+    let now = time.elapsed_seconds();
+    let trick_inputs = [
+        (input_map.forward, TrickInput::Forward),
+        (input_map.back, TrickInput::Back),
+        (input_map.left, TrickInput::Left),
+        (input_map.right, TrickInput::Right),
+        (input_map.trick_flip, TrickInput::Flip),
+        (input_map.trick_grab, TrickInput::Grab),
+    ];
+    for (key_code, trick_input) in trick_inputs {
+        if !key.just_pressed(key_code) {
+            continue;
+        }
+        trick_state.inputs.retain(|(_input, pressed)| now - pressed <= TRICK_INPUT_WINDOW);
+        trick_state.inputs.push((trick_input, now));
+        if trick_state.inputs.len() > TRICK_INPUT_BUFFER_LENGTH {
+            trick_state.inputs.remove(0);
+        }
+        if !matches!(trick_input, TrickInput::Flip | TrickInput::Grab) || trick_state.active.is_some() {
+            continue;
+        }
+
+        let inputs: Vec<TrickInput> = trick_state.inputs.iter().map(|(input, _pressed)| *input).collect();
+        if let Some(trick) = trick_table.find(&inputs) {
+            debug!(trick = %trick.name, "Trick started");
+            trick_state.active = Some(ActiveTrick {
+                trick: trick.clone(),
+                elapsed: 0.0,
+            });
+            trick_state.inputs.clear();
+        }
+    }
+}
+
+/// Turns the body through the active trick, on top of the lean set by `handle_player_movement`.
+pub fn apply_trick_rotation(
+    mut trick_state: ResMut<TrickState>,
+    mut q_player_body: Query<&mut Transform, With<PlayerBody>>,
+    time: Res<Time>,
+) {
+    let Some(active) = trick_state.active.as_mut() else {
+        return;
+    };
+    active.elapsed += time.delta().as_secs_f32();
+    let rotation = active.rotation();
+    let finished = active.progress() >= 1.0;
+    for mut player_body_transform in q_player_body.iter_mut() {
+        player_body_transform.rotation *= rotation;
+    }
+    if finished {
+        if let Some(active) = trick_state.active.take() {
+            trick_state.completed.push(active.trick);
+        }
+    }
+}
+
+pub fn handle_trick_landing(
+    mut ev_player_landed: EventReader<PlayerLandedEvent>,
+    mut ev_player_bail: EventWriter<PlayerBailEvent>,
+    mut ev_trick_landed: EventWriter<TrickLandedEvent>,
+    mut ev_trick_failed: EventWriter<TrickFailedEvent>,
+    mut trick_state: ResMut<TrickState>,
+    q_player: Query<(Entity, &Player)>,
+) {
+    let Ok((player_entity, player)) = q_player.get_single() else {
+        return;
+    };
+    if player.bailed {
+        trick_state.reset();
+        return;
+    }
+    for _ev in ev_player_landed.read() {
+        let sloppy = trick_state.active.as_ref().filter(|active| !active.is_landable());
+        if let Some(active) = sloppy {
+            info!(trick = %active.trick.name, progress = active.progress(), "Trick failed");
+            ev_trick_failed.send(TrickFailedEvent {
+                name: active.trick.name.to_owned(),
+            });
+            ev_player_bail.send(PlayerBailEvent((player_entity, true)));
+            trick_state.reset();
+            continue;
+        }
+
+        // Nearly finished tricks are snapped to done on landing
+        if let Some(active) = trick_state.active.take() {
+            trick_state.completed.push(active.trick);
+        }
+        for trick in trick_state.completed.drain(..) {
+            info!(trick = %trick.name, points = trick.points, "Trick landed");
+            ev_trick_landed.send(TrickLandedEvent {
+                name: trick.name,
+                points: trick.points,
+            });
+        }
+        trick_state.reset();
+    }
+}
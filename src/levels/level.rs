@@ -0,0 +1,626 @@
+use std::f32::consts::FRAC_PI_2;
+
+use avian3d::prelude::{AngularVelocity, Collider, ColliderConstructor, ColliderConstructorHierarchy, CollisionLayers, Friction, LayerMask, RigidBody, Sensor};
+use bevy::{asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext}, prelude::*, render::mesh::ConeMeshBuilder};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{entities::{collectibles::{Collectible, CollectibleRespawn}, player::player::Player, world_objects::{Checkpoint, PlayerRespawn, SpawnPoint}, EntityCollisionLayers}, gameplay::{grind::{Grindable, GrindableKind}, objectives::LevelObjective, race::RaceGate, transition::Transition}};
+
+use super::ramps::{half_pipe_profile, quarter_pipe_profile, ramp_profile, ExtrudedProfile, DECK_DEPTH};
+
+pub const DEFAULT_LEVEL_PATH: &str = "levels/default.level.ron";
+
+const CONE_RESOLUTION: u32 = 16;
+const COLLECTIBLE_RADIUS: f32 = 0.3;
+
+#[derive(Asset, TypePath, Serialize, Deserialize, Clone, Default)]
+pub struct Level {
+    #[serde(default)]
+    pub spawn_points: Vec<LevelSpawnPoint>,
+    #[serde(default)]
+    pub checkpoints: Vec<LevelCheckpoint>,
+    #[serde(default)]
+    pub gates: Vec<LevelGate>,
+    #[serde(default)]
+    pub collectibles: Vec<LevelCollectible>,
+    #[serde(default)]
+    pub objects: Vec<LevelObject>,
+    #[serde(default)]
+    pub rails: Vec<LevelRail>,
+    #[serde(default)]
+    pub lights: Vec<LevelLight>,
+    #[serde(default)]
+    pub objectives: Vec<LevelObjective>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LevelSpawnPoint {
+    pub location: Vec3,
+    #[serde(default)]
+    pub rotation: Vec3,
+    #[serde(default)]
+    pub default: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LevelCheckpoint {
+    pub location: Vec3,
+    #[serde(default)]
+    pub rotation: Vec3,
+    #[serde(default = "default_checkpoint_size")]
+    pub size: Vec3,
+}
+
+/// A race gate, passed through in the order gates are listed.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LevelGate {
+    pub location: Vec3,
+    #[serde(default)]
+    pub rotation: Vec3,
+    #[serde(default = "default_checkpoint_size")]
+    pub size: Vec3,
+}
+
+/// A pickup, saved as collected by its `id`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LevelCollectible {
+    pub id: String,
+    pub location: Vec3,
+    #[serde(default)]
+    pub respawn: CollectibleRespawn,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct LevelObject {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub shape: LevelShape,
+    #[serde(default)]
+    pub location: Vec3,
+    #[serde(default)]
+    pub rotation: Vec3,
+    #[serde(default = "default_scale")]
+    pub scale: Vec3,
+    #[serde(default)]
+    pub body: LevelBody,
+    #[serde(default)]
+    pub layer: EntityCollisionLayers,
+    #[serde(default)]
+    pub material: LevelMaterial,
+    #[serde(default)]
+    pub friction: Option<f32>,
+    #[serde(default)]
+    pub angular_velocity: Option<Vec3>,
+    #[serde(default = "default_count")]
+    pub count: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub enum LevelShape {
+    Cuboid { x: f32, y: f32, z: f32 },
+    Cylinder { radius: f32, height: f32 },
+    Cone { radius: f32, height: f32 },
+    Sphere { radius: f32 },
+    Capsule { radius: f32, length: f32 },
+    Ramp { width: f32, length: f32, height: f32 },
+    QuarterPipe { radius: f32, width: f32, height: f32 },
+    HalfPipe { radius: f32, width: f32, height: f32, flat: f32 },
+    Gltf { path: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+pub enum LevelBody {
+    #[default]
+    Static,
+    Dynamic,
+    Kinematic,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LevelMaterial {
+    pub color: (u8, u8, u8),
+    #[serde(default = "default_roughness")]
+    pub roughness: f32,
+}
+
+/// A grindable polyline, with a round bar mesh and collider for rails.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LevelRail {
+    pub points: Vec<Vec3>,
+    #[serde(default)]
+    pub kind: GrindableKind,
+    #[serde(default = "default_rail_radius")]
+    pub radius: f32,
+    #[serde(default)]
+    pub material: LevelMaterial,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub enum LevelLight {
+    Point {
+        location: Vec3,
+        #[serde(default)]
+        shadows: bool,
+    },
+}
+
+/// Marks every entity spawned from a [`Level`] so it can be cleared on reload.
+#[derive(Component)]
+pub struct LevelEntity;
+
+/// Asks for the current level to be spawned again, putting moved props back where the level file has them.
+#[derive(Event)]
+pub struct LevelRespawnEvent;
+
+#[derive(Resource)]
+pub struct CurrentLevel {
+    pub handle: Handle<Level>,
+}
+
+#[derive(Default)]
+pub struct LevelLoader;
+
+#[derive(Debug, Error)]
+pub enum LevelLoaderError {
+    #[error("Could not read level file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse level file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+fn default_scale() -> Vec3 {
+    Vec3::ONE
+}
+
+fn default_checkpoint_size() -> Vec3 {
+    Vec3::new(4.0, 4.0, 1.0)
+}
+
+fn default_rail_radius() -> f32 {
+    0.05
+}
+
+fn default_count() -> usize {
+    1
+}
+
+fn default_roughness() -> f32 {
+    0.5
+}
+
+impl Default for LevelMaterial {
+    fn default() -> Self {
+        Self {
+            color: (255, 255, 255),
+            roughness: default_roughness(),
+        }
+    }
+}
+
+impl From<LevelBody> for RigidBody {
+    fn from(body: LevelBody) -> Self {
+        match body {
+            LevelBody::Static => RigidBody::Static,
+            LevelBody::Dynamic => RigidBody::Dynamic,
+            LevelBody::Kinematic => RigidBody::Kinematic,
+        }
+    }
+}
+
+impl From<RigidBody> for LevelBody {
+    fn from(body: RigidBody) -> Self {
+        match body {
+            RigidBody::Static => LevelBody::Static,
+            RigidBody::Dynamic => LevelBody::Dynamic,
+            RigidBody::Kinematic => LevelBody::Kinematic,
+        }
+    }
+}
+
+impl LevelShape {
+    pub fn collider(&self) -> Option<Collider> {
+        match *self {
+            LevelShape::Cuboid { x, y, z } => Some(Collider::cuboid(x, y, z)),
+            LevelShape::Cylinder { radius, height } => Some(Collider::cylinder(radius, height)),
+            LevelShape::Cone { radius, height } => Some(Collider::cone(radius, height)),
+            LevelShape::Sphere { radius } => Some(Collider::sphere(radius)),
+            LevelShape::Capsule { radius, length } => Some(Collider::capsule(radius, length)),
+            LevelShape::Ramp { .. } | LevelShape::QuarterPipe { .. } | LevelShape::HalfPipe { .. } => self.extruded().map(|solid| solid.collider()),
+            LevelShape::Gltf { .. } => None,
+        }
+    }
+    pub fn mesh(&self) -> Option<Mesh> {
+        match *self {
+            LevelShape::Cuboid { x, y, z } => Some(Cuboid::new(x, y, z).into()),
+            LevelShape::Cylinder { radius, height } => Some(Cylinder::new(radius, height).into()),
+            LevelShape::Cone { radius, height } => Some(ConeMeshBuilder::new(radius, height, CONE_RESOLUTION).into()),
+            LevelShape::Sphere { radius } => Some(Sphere::new(radius).into()),
+            LevelShape::Capsule { radius, length } => Some(Capsule3d::new(radius, length).into()),
+            LevelShape::Ramp { .. } | LevelShape::QuarterPipe { .. } | LevelShape::HalfPipe { .. } => self.extruded().map(|solid| solid.mesh()),
+            LevelShape::Gltf { .. } => None,
+        }
+    }
+    /// The rideable surface of ramps and pipes.
+    pub fn transition(&self) -> Option<Transition> {
+        match *self {
+            LevelShape::Ramp { width, length, height } => Some(Transition {
+                profile: ramp_profile(length, height),
+                width,
+            }),
+            LevelShape::QuarterPipe { radius, width, height } => Some(Transition {
+                profile: quarter_pipe_profile(radius, height),
+                width,
+            }),
+            LevelShape::HalfPipe { radius, width, height, flat } => Some(Transition {
+                profile: half_pipe_profile(radius, height, flat),
+                width,
+            }),
+            _ => None,
+        }
+    }
+    /// Solid for ramps and pipes, with a deck behind every lip.
+    fn extruded(&self) -> Option<ExtrudedProfile> {
+        let transition = self.transition()?;
+        let deck = Vec2::new(DECK_DEPTH, 0.0);
+        let mut outline = transition.profile.to_owned();
+        match *self {
+            LevelShape::QuarterPipe { .. } => {
+                outline.push(*transition.profile.last()? + deck);
+            },
+            LevelShape::HalfPipe { .. } => {
+                outline.insert(0, *transition.profile.first()? - deck);
+                outline.push(*transition.profile.last()? + deck);
+            },
+            _ => {},
+        }
+        Some(ExtrudedProfile::new(&outline, transition.width))
+    }
+}
+
+impl LevelObject {
+    pub fn transform(&self) -> Transform {
+        Transform {
+            translation: self.location,
+            rotation: Quat::from_euler(EulerRot::XYZ,
+                self.rotation.x,
+                self.rotation.y,
+                self.rotation.z),
+            scale: self.scale,
+        }
+    }
+    pub fn spawn(&self,
+            mut commands: Commands,
+            asset_server: &AssetServer,
+            mut meshes: Mut<Assets<Mesh>>,
+            mut materials: Mut<Assets<StandardMaterial>>) -> Entity {
+        let transform = self.transform();
+        let collision_layers = CollisionLayers::new(self.layer, LayerMask::ALL);
+        let mut object_entity = match &self.shape {
+            LevelShape::Gltf { path } => commands.spawn((
+                ColliderConstructorHierarchy::new(Some(ColliderConstructor::TrimeshFromMesh))
+                    .with_default_layers(collision_layers),
+                SceneBundle {
+                    scene: asset_server.load(GltfAssetLabel::Scene(0).from_asset(path.to_owned())),
+                    transform,
+                    ..default()
+                },
+            )),
+            shape => commands.spawn((
+                shape.collider().unwrap(),
+                collision_layers,
+                PbrBundle {
+                    mesh: meshes.add(shape.mesh().unwrap()),
+                    material: materials.add(StandardMaterial {
+                        base_color: Color::srgb_u8(
+                            self.material.color.0,
+                            self.material.color.1,
+                            self.material.color.2),
+                        perceptual_roughness: self.material.roughness,
+                        ..default()
+                    }),
+                    transform,
+                    ..default()
+                },
+            )),
+        };
+        object_entity.insert((
+            LevelEntity,
+            RigidBody::from(self.body),
+            LevelObject {
+                count: 1,
+                ..self.clone()
+            },
+        ));
+        if let Some(name) = &self.name {
+            object_entity.insert(Name::new(name.to_owned()));
+        }
+        if let Some(friction) = self.friction {
+            object_entity.insert(Friction::new(friction));
+        }
+        if let Some(angular_velocity) = self.angular_velocity {
+            object_entity.insert(AngularVelocity(angular_velocity));
+        }
+        if let Some(transition) = self.shape.transition() {
+            object_entity.insert(transition);
+        }
+        object_entity.id()
+    }
+}
+
+impl LevelRail {
+    pub fn spawn(&self,
+            mut commands: Commands,
+            mut meshes: Mut<Assets<Mesh>>,
+            mut materials: Mut<Assets<StandardMaterial>>) -> Entity {
+        let mut rail = commands.spawn((
+            LevelEntity,
+            Grindable {
+                points: self.points.to_owned(),
+                kind: self.kind,
+            },
+            Name::new(format!("{:?}", self.kind)),
+            SpatialBundle::default(),
+        ));
+        // Ledges run along the edge of other geometry, so only rails get a body of their own
+        if self.kind == GrindableKind::Rail && self.points.len() > 1 {
+            let segments = self.points.windows(2)
+                .map(|segment| (Vec3::ZERO, Quat::IDENTITY, Collider::capsule_endpoints(self.radius, segment[0], segment[1])))
+                .collect();
+            let material = materials.add(StandardMaterial {
+                base_color: Color::srgb_u8(
+                    self.material.color.0,
+                    self.material.color.1,
+                    self.material.color.2),
+                perceptual_roughness: self.material.roughness,
+                ..default()
+            });
+            rail.insert((
+                RigidBody::Static,
+                Collider::compound(segments),
+                CollisionLayers::new(EntityCollisionLayers::Ground, LayerMask::ALL),
+            )).with_children(|parent| {
+                for segment in self.points.windows(2) {
+                    let (start, end) = (segment[0], segment[1]);
+                    parent.spawn(PbrBundle {
+                        mesh: meshes.add(Cylinder::new(self.radius, start.distance(end))),
+                        material: material.clone(),
+                        transform: Transform::from_translation((start + end) / 2.0)
+                            .with_rotation(Quat::from_rotation_arc(Vec3::Y, (end - start).normalize())),
+                        ..default()
+                    });
+                }
+            });
+        }
+        rail.id()
+    }
+}
+
+impl Level {
+    /// The spawn point marked as default, falling back to the first one listed.
+    pub fn default_spawn_point(&self) -> Option<&LevelSpawnPoint> {
+        self.spawn_points.iter()
+            .find(|spawn_point| spawn_point.default)
+            .or(self.spawn_points.first())
+    }
+    pub fn spawn(&self,
+            mut commands: Commands,
+            asset_server: &AssetServer,
+            mut meshes: Mut<Assets<Mesh>>,
+            mut materials: Mut<Assets<StandardMaterial>>) {
+        for object in self.objects.iter() {
+            for _i in 0..object.count {
+                object.spawn(commands.reborrow(),
+                    asset_server,
+                    meshes.reborrow(),
+                    materials.reborrow());
+            }
+        }
+        for spawn_point in self.spawn_points.iter() {
+            commands.spawn((
+                LevelEntity,
+                SpawnPoint {
+                    rotation: spawn_point.rotation,
+                    default: spawn_point.default,
+                },
+                SpatialBundle::from_transform(Transform::from_translation(spawn_point.location)),
+            ));
+        }
+        for checkpoint in self.checkpoints.iter() {
+            commands.spawn((
+                LevelEntity,
+                Checkpoint {
+                    rotation: checkpoint.rotation,
+                },
+                Name::new("Checkpoint"),
+                Sensor,
+                Collider::cuboid(checkpoint.size.x, checkpoint.size.y, checkpoint.size.z),
+                CollisionLayers::new(EntityCollisionLayers::Triggers, EntityCollisionLayers::Player),
+                SpatialBundle::from_transform(Transform {
+                    translation: checkpoint.location,
+                    rotation: Quat::from_euler(EulerRot::XYZ,
+                        checkpoint.rotation.x,
+                        checkpoint.rotation.y,
+                        checkpoint.rotation.z),
+                    ..default()
+                }),
+            ));
+        }
+        if !self.gates.is_empty() {
+            let gate_material = materials.add(StandardMaterial {
+                base_color: Color::srgba(0.3, 0.8, 1.0, 0.15),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            });
+            for (index, gate) in self.gates.iter().enumerate() {
+                commands.spawn((
+                    LevelEntity,
+                    RaceGate {
+                        index,
+                    },
+                    Name::new(format!("Gate {}", index)),
+                    Sensor,
+                    Collider::cuboid(gate.size.x, gate.size.y, gate.size.z),
+                    CollisionLayers::new(EntityCollisionLayers::Triggers, EntityCollisionLayers::Player),
+                    PbrBundle {
+                        mesh: meshes.add(Cuboid::new(gate.size.x, gate.size.y, gate.size.z)),
+                        material: gate_material.clone(),
+                        transform: Transform {
+                            translation: gate.location,
+                            rotation: Quat::from_euler(EulerRot::XYZ,
+                                gate.rotation.x,
+                                gate.rotation.y,
+                                gate.rotation.z),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                ));
+            }
+        }
+        if !self.collectibles.is_empty() {
+            // Upright coin that spins about Y
+            let collectible_mesh = meshes.add(Mesh::from(Cylinder::new(COLLECTIBLE_RADIUS, 0.05))
+                .rotated_by(Quat::from_rotation_x(FRAC_PI_2)));
+            let collectible_material = materials.add(StandardMaterial {
+                base_color: Color::srgb_u8(240, 200, 60),
+                metallic: 0.8,
+                perceptual_roughness: 0.3,
+                ..default()
+            });
+            for collectible in self.collectibles.iter() {
+                commands.spawn((
+                    LevelEntity,
+                    Collectible {
+                        id: collectible.id.to_owned(),
+                        respawn: collectible.respawn,
+                        respawn_timer: None,
+                    },
+                    Name::new(format!("Collectible {}", collectible.id)),
+                    Sensor,
+                    Collider::sphere(COLLECTIBLE_RADIUS * 1.5),
+                    CollisionLayers::new(EntityCollisionLayers::Collectibles, EntityCollisionLayers::Player),
+                    PbrBundle {
+                        mesh: collectible_mesh.clone(),
+                        material: collectible_material.clone(),
+                        transform: Transform::from_translation(collectible.location),
+                        ..default()
+                    },
+                ));
+            }
+        }
+        for rail in self.rails.iter() {
+            rail.spawn(commands.reborrow(),
+                meshes.reborrow(),
+                materials.reborrow());
+        }
+        for light in self.lights.iter() {
+            match *light {
+                LevelLight::Point { location, shadows } => {
+                    commands.spawn((
+                        LevelEntity,
+                        PointLightBundle {
+                            point_light: PointLight {
+                                shadows_enabled: shadows,
+                                ..default()
+                            },
+                            transform: Transform::from_translation(location),
+                            ..default()
+                        },
+                    ));
+                }
+            }
+        }
+    }
+}
+
+impl AssetLoader for LevelLoader {
+    type Asset = Level;
+    type Settings = ();
+    type Error = LevelLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let level = ron::de::from_bytes::<Level>(&bytes)?;
+        Ok(level)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+pub fn load_level(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands.insert_resource(CurrentLevel {
+        handle: asset_server.load(DEFAULT_LEVEL_PATH),
+    });
+}
+
+// (re)spawn the level whenever its file finishes loading, changes on disk or a respawn is asked for
+#[allow(clippy::too_many_arguments)]
+pub fn handle_level_asset_events(
+    mut commands: Commands,
+    mut ev_level_asset: EventReader<AssetEvent<Level>>,
+    mut ev_level_respawn: EventReader<LevelRespawnEvent>,
+    asset_server: Res<AssetServer>,
+    current_level: Res<CurrentLevel>,
+    levels: Res<Assets<Level>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    q_level_entities: Query<Entity, With<LevelEntity>>,
+    mut player_respawn: ResMut<PlayerRespawn>,
+    q_player: Query<(), With<Player>>,
+) {
+    let mut respawn = false;
+    for ev in ev_level_asset.read() {
+        if ev.is_loaded_with_dependencies(&current_level.handle)
+            || ev.is_modified(&current_level.handle) {
+            respawn = true;
+        }
+    }
+    if ev_level_respawn.read().count() > 0 {
+        respawn = true;
+    }
+    if !respawn {
+        return;
+    }
+    let Some(level) = levels.get(&current_level.handle) else {
+        return;
+    };
+
+    // Clear previously spawned level entities
+    for level_entity in q_level_entities.iter() {
+        commands.entity(level_entity).despawn_recursive();
+    }
+
+    level.spawn(commands.reborrow(),
+        &asset_server,
+        meshes.reborrow(),
+        materials.reborrow());
+
+    // Only spawn the player on first load so hot reloads keep the current run
+    if q_player.is_empty() {
+        let spawn_point = level.default_spawn_point();
+        *player_respawn = PlayerRespawn {
+            location: spawn_point.map(|spawn_point| spawn_point.location).unwrap_or_default(),
+            rotation: spawn_point.map(|spawn_point| spawn_point.rotation).unwrap_or_default(),
+            checkpoint: None,
+            pending: false,
+        };
+        Player::spawn(commands.reborrow(),
+            meshes.reborrow(),
+            materials.reborrow(),
+            Some(player_respawn.location),
+            Some(player_respawn.rotation));
+    }
+}
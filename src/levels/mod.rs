@@ -0,0 +1,2 @@
+pub mod level;
+pub mod ramps;
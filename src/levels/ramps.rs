@@ -0,0 +1,134 @@
+use std::f32::consts::FRAC_PI_2;
+
+use avian3d::prelude::Collider;
+use bevy::{prelude::*, render::{mesh::{Indices, PrimitiveTopology}, render_asset::RenderAssetUsages}};
+
+/// Segments used for a full quarter circle of transition.
+const TRANSITION_RESOLUTION: usize = 16;
+/// Depth of the flat deck behind the lip of a quarter or half pipe.
+pub const DECK_DEPTH: f32 = 1.0;
+
+/// Riding surface of a straight ramp, as local (z, y) points rising towards +Z.
+pub fn ramp_profile(length: f32, height: f32) -> Vec<Vec2> {
+    vec![Vec2::ZERO, Vec2::new(length, height)]
+}
+
+/// Riding surface of a quarter pipe, as local (z, y) points curving up towards +Z.
+/// The transition is cut off at `height` when it is lower than `radius`, otherwise it
+/// goes vertical and the rest of the height is straight vert.
+pub fn quarter_pipe_profile(radius: f32, height: f32) -> Vec<Vec2> {
+    let max_angle = if height < radius {
+        (1.0 - height / radius).acos()
+    } else {
+        FRAC_PI_2
+    };
+    let segments = ((TRANSITION_RESOLUTION as f32 * max_angle / FRAC_PI_2).ceil() as usize).max(1);
+    let mut profile: Vec<Vec2> = (0..=segments)
+        .map(|i| {
+            let angle = max_angle * i as f32 / segments as f32;
+            Vec2::new(radius * angle.sin(), radius * (1.0 - angle.cos()))
+        })
+        .collect();
+    if height > radius {
+        profile.push(Vec2::new(radius, height));
+    }
+    profile
+}
+
+/// Riding surface of a half pipe, two quarter pipes facing each other across a flat
+/// bottom, centred on the local origin.
+pub fn half_pipe_profile(radius: f32, height: f32, flat: f32) -> Vec<Vec2> {
+    let quarter_pipe = quarter_pipe_profile(radius, height);
+    let offset = flat / 2.0;
+    let mut profile: Vec<Vec2> = quarter_pipe.iter().rev()
+        .map(|point| Vec2::new(-point.x - offset, point.y))
+        .chain(quarter_pipe.iter().map(|point| Vec2::new(point.x + offset, point.y)))
+        .collect();
+    // Without a flat bottom both transitions start at the same point
+    profile.dedup();
+    profile
+}
+
+/// A closed solid built by extruding an outline across local X.
+pub struct ExtrudedProfile {
+    pub positions: Vec<Vec3>,
+    pub indices: Vec<[u32; 3]>,
+}
+
+impl ExtrudedProfile {
+    /// Extrudes `outline` (local (z, y) points with increasing z, all at or above y = 0)
+    /// across `width`, closing the sides and ends down to the ground.
+    pub fn new(outline: &[Vec2], width: f32) -> Self {
+        let mut solid = Self {
+            positions: Vec::new(),
+            indices: Vec::new(),
+        };
+        let half_width = width / 2.0;
+        let point = |x: f32, profile_point: Vec2| Vec3::new(x, profile_point.y, profile_point.x);
+        let ground = |profile_point: Vec2| Vec2::new(profile_point.x, 0.0);
+
+        for segment in outline.windows(2) {
+            let (start, end) = (segment[0], segment[1]);
+            // Riding surface
+            solid.push_quad([
+                point(-half_width, start),
+                point(-half_width, end),
+                point(half_width, end),
+                point(half_width, start),
+            ]);
+            // Sides
+            solid.push_quad([
+                point(half_width, ground(start)),
+                point(half_width, start),
+                point(half_width, end),
+                point(half_width, ground(end)),
+            ]);
+            solid.push_quad([
+                point(-half_width, ground(start)),
+                point(-half_width, ground(end)),
+                point(-half_width, end),
+                point(-half_width, start),
+            ]);
+        }
+        // Front and back walls
+        if let (Some(&first), Some(&last)) = (outline.first(), outline.last()) {
+            solid.push_quad([
+                point(-half_width, ground(first)),
+                point(-half_width, first),
+                point(half_width, first),
+                point(half_width, ground(first)),
+            ]);
+            solid.push_quad([
+                point(-half_width, ground(last)),
+                point(half_width, ground(last)),
+                point(half_width, last),
+                point(-half_width, last),
+            ]);
+        }
+        solid
+    }
+    /// Adds two triangles for a quad wound counter clockwise seen from outside,
+    /// skipping the ones that collapse where the outline touches the ground.
+    fn push_quad(&mut self, corners: [Vec3; 4]) {
+        for [a, b, c] in [[0, 1, 2], [0, 2, 3]] {
+            if (corners[b] - corners[a]).cross(corners[c] - corners[a]).length_squared() <= f32::EPSILON {
+                continue;
+            }
+            let index = self.positions.len() as u32;
+            self.positions.extend([corners[a], corners[b], corners[c]]);
+            self.indices.push([index, index + 1, index + 2]);
+        }
+    }
+    pub fn mesh(&self) -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions.to_owned())
+            .with_inserted_indices(Indices::U32(self.indices.iter().flatten().copied().collect()));
+        // Flat shading keeps the facets of the transition readable
+        mesh.duplicate_vertices();
+        mesh.compute_flat_normals();
+        mesh
+    }
+    pub fn collider(&self) -> Collider {
+        Collider::trimesh(self.positions.to_owned(), self.indices.to_owned())
+    }
+}
@@ -1,35 +1,314 @@
-use avian3d::{prelude::{AngularVelocity, Collider, CollisionLayers, Friction, LayerMask, PhysicsDebugPlugin, RigidBody}, PhysicsPlugins};
-use bevy::{prelude::*, render::mesh::ConeMeshBuilder};
-use controls::{controls::{handle_cursor, handle_debug_keys, handle_key_window_functions}, player::{handle_player_camera, handle_bailed_player_movement, handle_player_is_on_floor, handle_player_movement}};
-use entities::{player::player::{handle_player_bail, Player, PlayerBailEvent}, EntityCollisionLayers};
-use utils::debug::{setup_debug_screen, update_debug_screen};
+use std::time::Duration;
+
+use avian3d::{prelude::{PhysicsDebugPlugin, PhysicsSet}, PhysicsPlugins};
+use bevy::{app::ScheduleRunnerPlugin, diagnostic::{Diagnostic, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin, RegisterDiagnostic}, input::InputSystem, log::LogPlugin, prelude::*, render::{settings::WgpuSettings, RenderPlugin}, time::TimeSystem, window::ExitCondition, winit::WinitPlugin};
+use controls::{controls::{handle_cursor, handle_debug_keys, handle_key_window_functions}, noclip::{handle_noclip_camera, handle_noclip_keys, handle_noclip_toggle, noclip_command, noclip_enabled, Noclip, NoclipToggleEvent}, player::{handle_player_camera, handle_bailed_player_movement, handle_player_is_on_floor, handle_player_movement, PlayerTuning}, replay::{play_input, record_command, record_input, replay_command, skip_main_menu_for_replay, start_pending_recording, start_pending_replay, InputReplay, ReplayArgs}};
+use editor::editor::{draw_editor_gizmos, editor_enabled, editor_palette, handle_editor_camera, handle_editor_palette, handle_editor_save, handle_editor_select, handle_editor_toggle, handle_editor_transform, update_editor_status, Editor};
+use entities::{collectibles::{handle_collectibles, load_collected_items, remove_collected_items, spin_collectibles, update_collectible_respawns, CollectedItems, CollectiblePickedUpEvent}, player::player::{handle_player_bail, Player, PlayerBailEvent}, world_objects::{handle_checkpoints, handle_player_respawn, PlayerRespawn}};
+use gameplay::{grind::{handle_grind, handle_grind_snap, player_grinding, Grind, GrindEndedEvent, GrindStartedEvent}, hud::HudPlugin, landing::{handle_landing_assist, handle_landing_quality, LandingState, PlayerLandingEvent}, manual::{apply_manual_pitch, handle_manual, Manual, ManualEndedEvent}, objectives::{handle_objectives, load_objectives, ObjectiveCompletedEvent, Objectives}, race::{cancel_race_run, handle_race_gates, load_personal_best, spawn_race_ghost, update_race_ghost, update_race_timer, Race, RaceFinishedEvent, RaceSplitEvent}, score::{handle_score, handle_score_bail, ComboEndedEvent, Score}, transition::{handle_transition_entry, handle_transition_ride, player_riding_transition, TransitionRide}, tricks::{apply_trick_rotation, handle_player_airtime, handle_trick_input, handle_trick_landing, PlayerAirtime, PlayerLandedEvent, PlayerTakeoffEvent, TrickFailedEvent, TrickLandedEvent, TrickState, TrickTable, TrickTableLoader}};
+use levels::level::{handle_level_asset_events, load_level, CurrentLevel, Level, LevelLoader, LevelRespawnEvent};
+use states::{game_state::{pause_physics, release_cursor, unpause_physics, GameState}, loading::{handle_loading, load_assets, setup_loading_error_screen, setup_loading_screen, LoadingErrors}, menu::{handle_menu_buttons, setup_main_menu, setup_pause_menu}};
+use utils::{collision_inspector::{collision_debug_panel, draw_collision_gizmos, inspect_command, record_collision_events, CollisionInspector}, console::{console_open, handle_console_input, setup_console, update_console_display, Console, ConsoleCommandAppExt, ConsoleCommands}, console_commands::{bail_command, gravity_command, set_command, spawn_command, timescale_command, tp_command}, debug::{collider_debug_panel, handle_debug_panel_keys, key_press_debug_panel, player_debug_panel, setup_debug_screen, DebugPanelAppExt, DebugPanels}, logging::{log_capture_layer, log_debug_panel, logfile_command, logfilter_command, LogPanelFilter, LOG_FILTER, LOG_LEVEL_NAMES}, perf::{perf_debug_panel, start_physics_step_timer, stop_physics_step_timer, PhysicsStepTimer, PHYSICS_STEP_TIME}, player_gizmos::{draw_player_gizmos, handle_player_gizmos_key, PlayerGizmos}, time_control::{apply_time_step, game_time_running, handle_time_control_keys, pause_command, step_command, time_debug_panel, TimeControl}};
 
 mod controls;
+mod editor;
 mod entities;
+mod gameplay;
+mod levels;
+mod states;
 mod utils;
 
 fn main() {
-    let plugins = (DefaultPlugins,
+    let replay_args = ReplayArgs::from_env();
+    let mut default_plugins = DefaultPlugins.set(LogPlugin {
+        filter: LOG_FILTER.to_owned(),
+        custom_layer: log_capture_layer,
+        ..default()
+    });
+    if replay_args.headless {
+        // No window or GPU, the app is driven by the schedule runner instead
+        default_plugins = default_plugins
+            .set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                ..default()
+            })
+            .set(RenderPlugin {
+                render_creation: WgpuSettings {
+                    backends: None,
+                    ..default()
+                }.into(),
+                ..default()
+            })
+            .disable::<WinitPlugin>()
+            .add(ScheduleRunnerPlugin::run_loop(Duration::ZERO));
+    }
+    let plugins = (default_plugins,
         PhysicsPlugins::default());
     let mut app = App::new();
-    app.add_plugins(plugins);
+    app.insert_resource(InputReplay::from(replay_args))
+        .add_plugins(plugins)
+        .init_state::<GameState>()
+        .enable_state_scoped_entities::<GameState>();
     if cfg!(debug_assertions) {
-        let debug_plugins = PhysicsDebugPlugin::default();
+        let debug_plugins = (PhysicsDebugPlugin::default(),
+            FrameTimeDiagnosticsPlugin,
+            EntityCountDiagnosticsPlugin);
         app.add_plugins(debug_plugins)
+            .register_diagnostic(Diagnostic::new(PHYSICS_STEP_TIME).with_suffix("ms"))
+            .init_resource::<PhysicsStepTimer>()
+            // Physics steps in the fixed timestep schedule, so the timer has to run there too
+            .add_systems(FixedPostUpdate, start_physics_step_timer.before(PhysicsSet::StepSimulation))
+            .add_systems(FixedPostUpdate, stop_physics_step_timer.after(PhysicsSet::StepSimulation))
+            .init_resource::<DebugPanels>()
+            .add_debug_panel("Performance", perf_debug_panel)
+            .add_debug_panel("Player", player_debug_panel)
+            .add_debug_panel("Keys", key_press_debug_panel)
+            .add_debug_panel("Colliders", collider_debug_panel)
+            .add_debug_panel("Time", time_debug_panel)
+            .add_debug_panel("Log", log_debug_panel)
+            .add_debug_panel("Collisions", collision_debug_panel)
+            .init_resource::<CollisionInspector>()
+            .add_console_command("inspect", "inspect [player|selected|off|<name>]: choose the entity whose collisions are listed", inspect_command)
+            .add_console_completions("inspect", &["player", "selected", "off"])
+            .add_systems(Update, (record_collision_events, draw_collision_gizmos).chain())
+            .init_gizmo_group::<PlayerGizmos>()
+            .add_systems(Update, (
+                handle_player_gizmos_key.run_if(not(console_open)),
+                draw_player_gizmos,
+            ).run_if(in_state(GameState::Playing)))
             .add_systems(Startup, setup_debug_screen)
-            .add_systems(Update, handle_debug_keys)
-            .add_systems(Update, update_debug_screen);
+            .add_systems(Update, handle_debug_panel_keys)
+            .add_systems(Update, handle_debug_keys.run_if(in_state(GameState::Playing)
+                .and_then(any_with_component::<Player>)
+                .and_then(not(editor_enabled))
+                .and_then(not(noclip_enabled))
+                .and_then(not(console_open))));
     }
+    let palette_names: Vec<String> = editor_palette().iter()
+        .filter_map(|level_object| level_object.name.as_ref().map(|name| name.to_lowercase()))
+        .collect();
     app.init_resource::<Game>()
-        .add_systems(Startup, setup)
+        .init_resource::<PlayerTuning>()
+        .init_resource::<Console>()
+        .init_resource::<ConsoleCommands>()
+        .add_console_command("tp", "tp <x> <y> <z>: teleport the player", tp_command)
+        .add_console_command("bail", "bail: toggle the player bail", bail_command)
+        .add_console_command("spawn", "spawn <object> [count]: spawn props in front of the player", spawn_command)
+        .add_console_completions("spawn", &palette_names.iter().map(|name| name.as_str()).collect::<Vec<&str>>())
+        .add_console_command("gravity", "gravity <y>: set world and player gravity", gravity_command)
+        .add_console_command("timescale", "timescale <scale>: scale game and physics time", timescale_command)
+        .add_console_command("pause", "pause: pause or resume game and physics time", pause_command)
+        .add_console_command("step", "step [frames]: advance paused time by fixed steps", step_command)
+        .add_console_command("set", "set <parameter> [value]: show or change a player tuning value", set_command)
+        .add_console_completions("set", &PlayerTuning::NAMES)
+        .add_console_command("record", "record <start|stop [path]>: record player input at a fixed timestep", record_command)
+        .add_console_completions("record", &["start", "stop"])
+        .add_console_command("replay", "replay [path]: play back recorded player input", replay_command)
+        .add_console_command("noclip", "noclip [speed <value>]: toggle the free-fly camera or set its speed", noclip_command)
+        .add_console_completions("noclip", &["speed"])
+        .init_resource::<LogPanelFilter>()
+        .add_console_command("logfilter", "logfilter [level] [target]: filter the log panel by level and target prefix", logfilter_command)
+        .add_console_completions("logfilter", &LOG_LEVEL_NAMES)
+        .add_console_command("logfile", "logfile <path|off>: append log events to a file", logfile_command)
+        .init_resource::<TimeControl>()
+        .add_systems(PreUpdate, apply_time_step)
+        .add_systems(Update, handle_time_control_keys.run_if(in_state(GameState::Playing)
+            .and_then(not(console_open))))
+        .add_systems(Startup, setup_console)
+        .add_systems(Update, (handle_console_input, update_console_display).chain())
+        .init_resource::<Editor>()
+        .init_resource::<LoadingErrors>()
+        .add_systems(OnEnter(GameState::Loading), (pause_physics, (load_assets, setup_loading_screen).chain()))
+        .add_systems(Update, handle_loading.run_if(in_state(GameState::Loading)))
+        .add_systems(OnEnter(GameState::LoadingError), setup_loading_error_screen)
+        .add_systems(OnEnter(GameState::MainMenu), (setup_main_menu, release_cursor, skip_main_menu_for_replay))
+        .add_systems(OnEnter(GameState::Paused), (setup_pause_menu, release_cursor))
+        .add_systems(OnEnter(GameState::Playing), unpause_physics)
+        .add_systems(OnExit(GameState::Playing), pause_physics)
+        .add_systems(Update, handle_menu_buttons)
+        .add_systems(Update, handle_editor_toggle.run_if(in_state(GameState::Playing)
+            .and_then(not(noclip_enabled))))
+        .add_systems(Update, (
+            handle_editor_camera,
+            handle_editor_select,
+            handle_editor_transform,
+            handle_editor_palette,
+            handle_editor_save,
+            draw_editor_gizmos,
+            update_editor_status,
+        ).run_if(in_state(GameState::Playing)
+            .and_then(editor_enabled)
+            .and_then(not(console_open))))
+        .init_asset::<Level>()
+        .init_asset_loader::<LevelLoader>()
+        .add_systems(Startup, load_level)
+        .add_event::<LevelRespawnEvent>()
+        // Before the fixed main loop, so a respawn asked for as a replay starts is in place for its first physics step
+        .add_systems(PreUpdate, handle_level_asset_events)
+        .init_resource::<PlayerRespawn>()
         .add_event::<PlayerBailEvent>()
-        .add_systems(Update,handle_player_is_on_floor)
-        .add_systems(Update,handle_player_camera)
-        .add_systems(Update,handle_player_movement)
-        .add_systems(Update,handle_bailed_player_movement)
-        .add_systems(Update, handle_player_bail)
-        .add_systems(Update, handle_cursor)
-        .add_systems(Update, handle_key_window_functions)
+        // Starting before time updates lets the first recorded or replayed frame use the replay timestep
+        .add_systems(First, (start_pending_recording, start_pending_replay)
+            .before(TimeSystem)
+            .run_if(in_state(GameState::Playing)
+                .and_then(any_with_component::<Player>)
+                .and_then(not(console_open))))
+        .add_systems(PreUpdate, (record_input, play_input).chain()
+            .after(InputSystem)
+            .run_if(in_state(GameState::Playing)
+                .and_then(any_with_component::<Player>)
+                .and_then(not(console_open))))
+        .init_resource::<Noclip>()
+        .add_event::<NoclipToggleEvent>()
+        .add_systems(Update, (
+            handle_noclip_keys.run_if(not(console_open)),
+            handle_noclip_toggle,
+            handle_noclip_camera.run_if(noclip_enabled.and_then(not(console_open))),
+        ).chain().run_if(in_state(GameState::Playing)
+            .and_then(not(editor_enabled))))
+        .add_systems(Update, handle_checkpoints.run_if(in_state(GameState::Playing)))
+        .init_resource::<CollectedItems>()
+        .add_event::<CollectiblePickedUpEvent>()
+        .add_systems(Update, (load_collected_items, remove_collected_items).chain())
+        .add_systems(Update, (
+            handle_collectibles,
+            update_collectible_respawns,
+            spin_collectibles,
+        ).run_if(in_state(GameState::Playing)))
+        .add_systems(Update, (
+            handle_player_is_on_floor.run_if(not(player_grinding)
+                .and_then(not(player_riding_transition))
+                .and_then(game_time_running)),
+            handle_player_camera,
+            handle_player_movement.run_if(not(player_grinding)
+                .and_then(not(player_riding_transition))
+                .and_then(game_time_running)),
+            handle_bailed_player_movement,
+            handle_player_respawn.before(handle_player_bail),
+            handle_player_bail,
+        ).run_if(in_state(GameState::Playing)
+            .and_then(any_with_component::<Player>)
+            .and_then(not(editor_enabled))
+            .and_then(not(noclip_enabled))
+            .and_then(not(console_open))))
+        .init_asset::<TrickTable>()
+        .init_asset_loader::<TrickTableLoader>()
+        .init_resource::<PlayerAirtime>()
+        .init_resource::<TrickState>()
+        .add_event::<PlayerTakeoffEvent>()
+        .add_event::<PlayerLandedEvent>()
+        .add_event::<TrickLandedEvent>()
+        .add_event::<TrickFailedEvent>()
+        .add_systems(Update, (
+            handle_player_airtime.after(handle_player_is_on_floor),
+            handle_trick_landing.after(handle_player_airtime).before(handle_player_bail),
+            handle_trick_input.after(handle_trick_landing).before(handle_player_movement),
+            apply_trick_rotation.after(handle_player_movement),
+        ).run_if(in_state(GameState::Playing)
+            .and_then(any_with_component::<Player>)
+            .and_then(not(editor_enabled))
+            .and_then(not(noclip_enabled))
+            .and_then(not(console_open))
+            .and_then(game_time_running)))
+        .init_resource::<LandingState>()
+        .add_event::<PlayerLandingEvent>()
+        .add_systems(Update, (
+            handle_landing_assist.after(handle_player_airtime).before(handle_player_movement),
+            handle_landing_quality.after(handle_trick_landing)
+                .after(handle_transition_entry)
+                .after(handle_grind_snap)
+                .before(handle_player_bail),
+        ).run_if(in_state(GameState::Playing)
+            .and_then(any_with_component::<Player>)
+            .and_then(not(editor_enabled))
+            .and_then(not(noclip_enabled))
+            .and_then(not(console_open))
+            .and_then(game_time_running)))
+        .init_resource::<TransitionRide>()
+        .add_systems(Update, (
+            handle_transition_entry.after(handle_player_movement),
+            handle_transition_ride.after(handle_transition_entry)
+                .before(handle_player_bail)
+                .before(apply_trick_rotation),
+        ).run_if(in_state(GameState::Playing)
+            .and_then(any_with_component::<Player>)
+            .and_then(not(player_grinding))
+            .and_then(not(editor_enabled))
+            .and_then(not(noclip_enabled))
+            .and_then(not(console_open))
+            .and_then(game_time_running)))
+        .init_resource::<Grind>()
+        .add_event::<GrindStartedEvent>()
+        .add_event::<GrindEndedEvent>()
+        .add_systems(Update, (
+            handle_grind_snap.after(handle_player_movement),
+            handle_grind.after(handle_grind_snap)
+                .before(handle_player_bail)
+                .before(apply_trick_rotation),
+        ).run_if(in_state(GameState::Playing)
+            .and_then(any_with_component::<Player>)
+            .and_then(not(editor_enabled))
+            .and_then(not(noclip_enabled))
+            .and_then(not(console_open))
+            .and_then(game_time_running)))
+        .init_resource::<Manual>()
+        .add_event::<ManualEndedEvent>()
+        .add_systems(Update, (
+            handle_manual.after(handle_player_movement)
+                .after(handle_transition_ride)
+                .after(handle_grind)
+                .before(handle_player_bail),
+            apply_manual_pitch.after(handle_manual).after(apply_trick_rotation),
+        ).run_if(in_state(GameState::Playing)
+            .and_then(any_with_component::<Player>)
+            .and_then(not(editor_enabled))
+            .and_then(not(noclip_enabled))
+            .and_then(not(console_open))
+            .and_then(game_time_running)))
+        .init_resource::<Score>()
+        .add_event::<ComboEndedEvent>()
+        .add_systems(Update, (
+            handle_score.after(handle_trick_landing).after(handle_grind).after(handle_manual),
+            handle_score_bail.after(handle_score),
+        ).run_if(in_state(GameState::Playing)
+            .and_then(any_with_component::<Player>)))
+        .init_resource::<Race>()
+        .add_event::<RaceSplitEvent>()
+        .add_event::<RaceFinishedEvent>()
+        .add_systems(Startup, spawn_race_ghost)
+        .add_systems(Update, load_personal_best.run_if(resource_exists_and_changed::<CurrentLevel>))
+        .add_systems(Update, (
+            cancel_race_run.run_if(noclip_enabled
+                .or_else(editor_enabled)
+                .or_else(console_open)),
+            update_race_timer.after(cancel_race_run)
+                .run_if(not(noclip_enabled)
+                    .and_then(not(editor_enabled))
+                    .and_then(not(console_open))),
+            handle_race_gates.after(update_race_timer)
+                .run_if(not(noclip_enabled)
+                    .and_then(not(editor_enabled))
+                    .and_then(not(console_open))),
+            update_race_ghost.after(handle_race_gates),
+        ).run_if(in_state(GameState::Playing)
+            .and_then(any_with_component::<Player>)))
+        .init_resource::<Objectives>()
+        .add_event::<ObjectiveCompletedEvent>()
+        .add_systems(Update, load_objectives)
+        .add_systems(Update, handle_objectives.after(handle_score)
+            .after(handle_collectibles)
+            .after(handle_grind)
+            .after(handle_manual)
+            .run_if(in_state(GameState::Playing)
+                .and_then(any_with_component::<Player>)))
+        .add_plugins(HudPlugin)
+        .add_systems(Update, handle_cursor.run_if(in_state(GameState::Playing)
+            .and_then(not(editor_enabled))
+            .and_then(not(console_open))))
+        .add_systems(Update, handle_key_window_functions
+            .run_if(not(console_open))
+            .before(handle_console_input))
         .run();
 }
 
@@ -49,84 +328,3 @@ impl Default for Game {
         }
     }
 }
-
-fn setup(
-    mut commands: Commands,
-    _asset_server: Res<AssetServer>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-) {
-    Player::spawn(commands.reborrow(), 
-        meshes.reborrow(),
-        materials.reborrow(),
-        Some(Vec3::new(0.0, 0.25, 5.0)),
-        None);
-
-    // // spawn generator
-    // commands.spawn(SceneBundle {
-    //     scene: asset_server.load(GltfAssetLabel::Scene(0).from_asset("models/Generator.glb")),
-    //     ..default()
-    // });
-    
-    // Static physics object with a collision shape
-    commands.spawn((
-        RigidBody::Static,
-        Collider::cylinder(200.0, 0.1),
-        CollisionLayers::new(EntityCollisionLayers::Ground, LayerMask::ALL),
-        Friction::new(0.5),
-        PbrBundle {
-            mesh: meshes.add(Cylinder::new(200.0, 0.1)),
-            material: materials.add(Color::WHITE),
-            transform: Transform::from_xyz(0.0, -0.05, 0.0),
-            ..default()
-        },
-    ));
-    commands.spawn((
-        RigidBody::Static,
-        Collider::cuboid(10.0, 10.0, 10.0),
-        CollisionLayers::new(EntityCollisionLayers::Ground, LayerMask::ALL),
-        PbrBundle {
-            mesh: meshes.add(Cuboid::new(10.0, 10.0, 10.0)),
-            material: materials.add(Color::srgb_u8(124, 144, 255)),
-            transform: Transform::from_xyz(0.0, 5.0, -20.0),
-            ..default()
-        },
-    ));
-    commands.spawn((
-        RigidBody::Static,
-        Collider::cone(10.0, 1.0),
-        CollisionLayers::new(EntityCollisionLayers::Ground, LayerMask::ALL),
-        PbrBundle {
-            mesh: meshes.add(ConeMeshBuilder::new(10.0, 1.0, 16)),
-            material: materials.add(Color::srgb_u8(124, 144, 255)),
-            transform: Transform::from_xyz(20.0, 0.5, -20.0),
-            ..default()
-        },
-    ));
-
-    // Dynamic physics object with a collision shape and initial angular velocity
-    for _i in 0..10 {
-        commands.spawn((
-            RigidBody::Dynamic,
-            Collider::cuboid(1.0, 1.0, 1.0),
-            CollisionLayers::new(EntityCollisionLayers::Props, LayerMask::ALL),
-            AngularVelocity(Vec3::new(2.5, 3.5, 1.5)),
-            PbrBundle {
-                mesh: meshes.add(Cuboid::new(1.0, 1.0, 1.0)),
-                material: materials.add(Color::srgb_u8(124, 144, 255)),
-                transform: Transform::from_xyz(0.0, 4.0, 0.0),
-                ..default()
-            },
-        ));
-    }
-
-    // Light
-    commands.spawn(PointLightBundle {
-        point_light: PointLight {
-            shadows_enabled: true,
-            ..default()
-        },
-        transform: Transform::from_xyz(4.0, 8.0, 4.0),
-        ..default()
-    });
-}
\ No newline at end of file
@@ -0,0 +1,28 @@
+use avian3d::prelude::{Physics, PhysicsTime};
+use bevy::{prelude::*, window::{CursorGrabMode, PrimaryWindow}};
+
+#[derive(States, Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GameState {
+    #[default]
+    Loading,
+    LoadingError,
+    MainMenu,
+    Playing,
+    Paused,
+}
+
+pub fn pause_physics(mut physics_time: ResMut<Time<Physics>>) {
+    physics_time.pause();
+}
+
+pub fn unpause_physics(mut physics_time: ResMut<Time<Physics>>) {
+    physics_time.unpause();
+}
+
+pub fn release_cursor(mut q_windows: Query<&mut Window, With<PrimaryWindow>>) {
+    let Ok(mut primary_window) = q_windows.get_single_mut() else {
+        return;
+    };
+    primary_window.cursor.grab_mode = CursorGrabMode::None;
+    primary_window.cursor.visible = true;
+}
\ No newline at end of file
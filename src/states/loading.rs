@@ -0,0 +1,234 @@
+use bevy::{asset::{LoadState, UntypedAssetId}, prelude::*};
+
+use crate::{gameplay::tricks::{TrickTable, TRICK_TABLE_PATH}, levels::level::{CurrentLevel, Level, LevelShape}};
+
+use super::{game_state::GameState, menu::MenuButton};
+
+pub const DEBUG_FONT: &str = "fonts/Roboto/Roboto-Light.ttf";
+pub const MENU_FONT: &str = "fonts/Roboto/Roboto-Bold.ttf";
+
+const PROGRESS_BAR_WIDTH: f32 = 400.0;
+const PROGRESS_BAR_HEIGHT: f32 = 16.0;
+const PROGRESS_BAR_BACKGROUND_COLOR: Color = Color::srgb(0.15, 0.15, 0.15);
+const PROGRESS_BAR_COLOR: Color = Color::srgb(0.49, 0.56, 1.0);
+const ERROR_COLOR: Color = Color::srgb(1.0, 0.4, 0.4);
+
+/// Handles every asset the game needs before it can be played, held for the lifetime of the app.
+#[derive(Resource)]
+pub struct GameAssets {
+    pub debug_font: Handle<Font>,
+    pub menu_font: Handle<Font>,
+    pub tricks: Handle<TrickTable>,
+    /// Models the level uses, `None` until the level file has been scanned for them.
+    pub scenes: Option<Vec<Handle<Scene>>>,
+}
+
+/// Assets that failed to load, with the reason, shown on the error screen.
+#[derive(Resource, Default)]
+pub struct LoadingErrors(pub Vec<String>);
+
+#[derive(Component)]
+pub struct LoadingProgressBar;
+
+#[derive(Component)]
+pub struct LoadingProgressText;
+
+impl GameAssets {
+    pub fn asset_ids(&self) -> Vec<UntypedAssetId> {
+        let mut asset_ids = vec![
+            self.debug_font.id().untyped(),
+            self.menu_font.id().untyped(),
+            self.tricks.id().untyped(),
+        ];
+        asset_ids.extend(self.scenes.iter().flatten().map(|scene| scene.id().untyped()));
+        asset_ids
+    }
+}
+
+pub fn load_assets(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands.insert_resource(GameAssets {
+        debug_font: asset_server.load(DEBUG_FONT),
+        menu_font: asset_server.load(MENU_FONT),
+        tricks: asset_server.load(TRICK_TABLE_PATH),
+        scenes: None,
+    });
+}
+
+pub fn setup_loading_screen(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+) {
+    commands.spawn((
+        StateScoped(GameState::Loading),
+        Camera2dBundle {
+            camera: Camera {
+                order: 1,
+                ..default()
+            },
+            ..default()
+        },
+    ));
+    commands.spawn((
+        StateScoped(GameState::Loading),
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            ..default()
+        },
+    )).with_children(|parent| {
+        parent.spawn((
+            LoadingProgressText,
+            TextBundle::from_section(
+                "Loading...",
+                TextStyle {
+                    font: game_assets.menu_font.clone(),
+                    font_size: 24.0,
+                    ..default()
+                },
+            ),
+        ));
+        parent.spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(PROGRESS_BAR_WIDTH),
+                height: Val::Px(PROGRESS_BAR_HEIGHT),
+                ..default()
+            },
+            background_color: PROGRESS_BAR_BACKGROUND_COLOR.into(),
+            ..default()
+        }).with_children(|parent| {
+            parent.spawn((
+                LoadingProgressBar,
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(0.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    background_color: PROGRESS_BAR_COLOR.into(),
+                    ..default()
+                },
+            ));
+        });
+    });
+}
+
+pub fn handle_loading(
+    asset_server: Res<AssetServer>,
+    current_level: Option<Res<CurrentLevel>>,
+    levels: Res<Assets<Level>>,
+    mut game_assets: ResMut<GameAssets>,
+    mut loading_errors: ResMut<LoadingErrors>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut q_progress_bar: Query<&mut Style, With<LoadingProgressBar>>,
+    mut q_progress_text: Query<&mut Text, With<LoadingProgressText>>,
+) {
+    let Some(current_level) = current_level else {
+        return;
+    };
+
+    // Models referenced by the level are only known once the level file itself is in
+    if let Some(level) = levels.get(&current_level.handle) {
+        if game_assets.scenes.is_none() {
+            game_assets.scenes = Some(level.objects.iter()
+                .filter_map(|object| match &object.shape {
+                    LevelShape::Gltf { path } => Some(asset_server.load(GltfAssetLabel::Scene(0).from_asset(path.to_owned()))),
+                    _ => None,
+                })
+                .collect());
+        }
+    }
+
+    let mut asset_ids = game_assets.asset_ids();
+    asset_ids.push(current_level.handle.id().untyped());
+
+    let mut loaded = 0;
+    let mut errors = Vec::new();
+    for asset_id in asset_ids.iter() {
+        match asset_server.get_load_state(*asset_id) {
+            Some(LoadState::Failed(error)) => {
+                errors.push(error.to_string());
+            },
+            Some(LoadState::Loaded) if asset_server.is_loaded_with_dependencies(*asset_id) => {
+                loaded += 1;
+            },
+            _ => {}
+        }
+    }
+
+    if !errors.is_empty() {
+        loading_errors.0 = errors;
+        next_state.set(GameState::LoadingError);
+        return;
+    }
+
+    let progress = loaded as f32 / asset_ids.len() as f32;
+    for mut progress_bar_style in q_progress_bar.iter_mut() {
+        progress_bar_style.width = Val::Percent(progress * 100.0);
+    }
+    for mut progress_text in q_progress_text.iter_mut() {
+        progress_text.sections[0].value = format!("Loading... {}/{}", loaded, asset_ids.len());
+    }
+
+    if loaded == asset_ids.len() {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+pub fn setup_loading_error_screen(
+    mut commands: Commands,
+    loading_errors: Res<LoadingErrors>,
+) {
+    // Fonts may be what failed, so fall back to the default font
+    let text_style = TextStyle {
+        font_size: 20.0,
+        color: ERROR_COLOR,
+        ..default()
+    };
+    commands.spawn((
+        StateScoped(GameState::LoadingError),
+        Camera2dBundle {
+            camera: Camera {
+                order: 1,
+                ..default()
+            },
+            ..default()
+        },
+    ));
+    commands.spawn((
+        StateScoped(GameState::LoadingError),
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            ..default()
+        },
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            "Failed to load required assets",
+            TextStyle {
+                font_size: 32.0,
+                ..text_style.clone()
+            },
+        ));
+        for error in loading_errors.0.iter() {
+            parent.spawn(TextBundle::from_section(error, text_style.clone()));
+        }
+        MenuButton::Quit.spawn(parent, default());
+    });
+}
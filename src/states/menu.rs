@@ -0,0 +1,138 @@
+use bevy::prelude::*;
+
+use super::{game_state::GameState, loading::GameAssets};
+
+const MENU_BACKGROUND_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.6);
+const BUTTON_COLOR: Color = Color::srgb(0.15, 0.15, 0.15);
+const BUTTON_HOVERED_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
+const BUTTON_PRESSED_COLOR: Color = Color::srgb(0.49, 0.56, 1.0);
+
+#[derive(Component, Clone, Copy)]
+pub enum MenuButton {
+    Play,
+    Resume,
+    MainMenu,
+    Quit,
+}
+
+impl MenuButton {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MenuButton::Play => "Play",
+            MenuButton::Resume => "Resume",
+            MenuButton::MainMenu => "Main Menu",
+            MenuButton::Quit => "Quit",
+        }
+    }
+    pub fn spawn(&self, parent: &mut ChildBuilder, font: Handle<Font>) {
+        parent.spawn((
+            *self,
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(240.0),
+                    height: Val::Px(48.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: BUTTON_COLOR.into(),
+                ..default()
+            },
+        )).with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                self.label(),
+                TextStyle {
+                    font,
+                    font_size: 24.0,
+                    ..default()
+                },
+            ));
+        });
+    }
+}
+
+fn spawn_menu(
+    mut commands: Commands,
+    font: Handle<Font>,
+    state: GameState,
+    title: &str,
+    buttons: &[MenuButton],
+) {
+    commands.spawn((
+        StateScoped(state),
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            background_color: MENU_BACKGROUND_COLOR.into(),
+            ..default()
+        },
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            title,
+            TextStyle {
+                font: font.clone(),
+                font_size: 48.0,
+                ..default()
+            },
+        ));
+        for button in buttons {
+            button.spawn(parent, font.clone());
+        }
+    });
+}
+
+pub fn setup_main_menu(
+    commands: Commands,
+    game_assets: Res<GameAssets>,
+) {
+    spawn_menu(commands,
+        game_assets.menu_font.clone(),
+        GameState::MainMenu,
+        "Melting Pot",
+        &[MenuButton::Play, MenuButton::Quit]);
+}
+
+pub fn setup_pause_menu(
+    commands: Commands,
+    game_assets: Res<GameAssets>,
+) {
+    spawn_menu(commands,
+        game_assets.menu_font.clone(),
+        GameState::Paused,
+        "Paused",
+        &[MenuButton::Resume, MenuButton::MainMenu, MenuButton::Quit]);
+}
+
+pub fn handle_menu_buttons(
+    mut q_buttons: Query<(&Interaction, &MenuButton, &mut BackgroundColor), Changed<Interaction>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut ev_app_exit: EventWriter<AppExit>,
+) {
+    for (interaction, menu_button, mut background_color) in q_buttons.iter_mut() {
+        match interaction {
+            Interaction::Pressed => {
+                *background_color = BUTTON_PRESSED_COLOR.into();
+                match menu_button {
+                    MenuButton::Play | MenuButton::Resume => next_state.set(GameState::Playing),
+                    MenuButton::MainMenu => next_state.set(GameState::MainMenu),
+                    MenuButton::Quit => {
+                        ev_app_exit.send(AppExit::Success);
+                    },
+                }
+            },
+            Interaction::Hovered => {
+                *background_color = BUTTON_HOVERED_COLOR.into();
+            },
+            Interaction::None => {
+                *background_color = BUTTON_COLOR.into();
+            },
+        }
+    }
+}
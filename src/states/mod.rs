@@ -0,0 +1,3 @@
+pub mod game_state;
+pub mod loading;
+pub mod menu;
\ No newline at end of file
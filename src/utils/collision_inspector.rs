@@ -0,0 +1,240 @@
+use std::collections::VecDeque;
+
+use avian3d::prelude::{ColliderParent, CollisionEnded, CollisionLayers, CollisionStarted, Collisions, Contacts, LayerMask, Position, Rotation};
+use bevy::{color::palettes::css::{ORANGE, RED, YELLOW}, prelude::*};
+
+use crate::{editor::editor::Editor, entities::{player::player::PlayerBody, EntityCollisionLayers}};
+
+use super::console::ConsoleCommandResult;
+
+const COLLISION_RECORD_LENGTH: usize = 8;
+const CONTACT_GIZMO_RADIUS: f32 = 0.05;
+const CONTACT_NORMAL_GIZMO_LENGTH: f32 = 0.5;
+
+/// Which entity the collision inspector follows.
+#[derive(Default, Clone, Copy, PartialEq)]
+pub enum CollisionInspectorTarget {
+    #[default]
+    Player,
+    Selected,
+    Entity(Entity),
+    Off,
+}
+
+pub struct CollisionRecord {
+    pub started: bool,
+    pub time: f32,
+    pub other_name: String,
+    pub other_layers: String,
+    pub contacts: Vec<(Vec3, Vec3)>,
+    pub impulse: f32,
+}
+
+#[derive(Resource, Default)]
+pub struct CollisionInspector {
+    pub target: CollisionInspectorTarget,
+    pub records: VecDeque<CollisionRecord>,
+}
+
+impl CollisionInspector {
+    fn push(&mut self, record: CollisionRecord) {
+        self.records.push_back(record);
+        if self.records.len() > COLLISION_RECORD_LENGTH {
+            self.records.pop_front();
+        }
+    }
+}
+
+fn resolve_target(
+    target: CollisionInspectorTarget,
+    editor: &Editor,
+    q_player_body: &Query<Entity, With<PlayerBody>>,
+) -> Option<Entity> {
+    match target {
+        CollisionInspectorTarget::Player => q_player_body.get_single().ok(),
+        CollisionInspectorTarget::Selected => editor.selected,
+        CollisionInspectorTarget::Entity(entity) => Some(entity),
+        CollisionInspectorTarget::Off => None,
+    }
+}
+
+/// Whether `collider` is the target itself or one of the colliders of the target body.
+fn is_target(collider: Entity, target: Entity, q_collider_parents: &Query<&ColliderParent>) -> bool {
+    collider == target || q_collider_parents.get(collider).is_ok_and(|collider_parent| collider_parent.get() == target)
+}
+
+fn layer_names(layers: Option<&CollisionLayers>) -> String {
+    let Some(layers) = layers else {
+        return "-".to_owned();
+    };
+    let names: Vec<String> = EntityCollisionLayers::ALL.iter()
+        .filter(|layer| layers.memberships.has_all(LayerMask::from(**layer)))
+        .map(|layer| format!("{:?}", layer))
+        .collect();
+    names.join("|")
+}
+
+/// Contact points and normals of `contacts` in world space, normals pointing away from `target`.
+fn world_contacts(
+    contacts: &Contacts,
+    target: Entity,
+    q_collider_parents: &Query<&ColliderParent>,
+    q_positions: &Query<(&Position, &Rotation)>,
+) -> Vec<(Vec3, Vec3)> {
+    let target_is_first = is_target(contacts.entity1, target, q_collider_parents);
+    let (Ok((position1, rotation1)), Ok((position2, rotation2))) = (q_positions.get(contacts.entity1), q_positions.get(contacts.entity2)) else {
+        return Vec::new();
+    };
+    contacts.manifolds.iter()
+        .flat_map(|manifold| manifold.contacts.iter())
+        .map(|contact| {
+            if target_is_first {
+                (contact.global_point1(position1, rotation1), contact.global_normal1(rotation1))
+            } else {
+                (contact.global_point2(position2, rotation2), contact.global_normal2(rotation2))
+            }
+        })
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn record_collision_events(
+    mut ev_collision_started: EventReader<CollisionStarted>,
+    mut ev_collision_ended: EventReader<CollisionEnded>,
+    mut collision_inspector: ResMut<CollisionInspector>,
+    editor: Res<Editor>,
+    collisions: Res<Collisions>,
+    time: Res<Time>,
+    q_player_body: Query<Entity, With<PlayerBody>>,
+    q_collider_parents: Query<&ColliderParent>,
+    q_positions: Query<(&Position, &Rotation)>,
+    q_details: Query<(Option<&Name>, Option<&CollisionLayers>)>,
+) {
+    let Some(target) = resolve_target(collision_inspector.target, &editor, &q_player_body) else {
+        ev_collision_started.clear();
+        ev_collision_ended.clear();
+        return;
+    };
+    let events = ev_collision_started.read()
+        .map(|CollisionStarted(entity1, entity2)| (true, *entity1, *entity2))
+        .chain(ev_collision_ended.read()
+            .map(|CollisionEnded(entity1, entity2)| (false, *entity1, *entity2)));
+    for (started, entity1, entity2) in events {
+        let other = if is_target(entity1, target, &q_collider_parents) {
+            entity2
+        } else if is_target(entity2, target, &q_collider_parents) {
+            entity1
+        } else {
+            continue;
+        };
+        let (other_name, other_layers) = q_details.get(other)
+            .map(|(name, layers)| (
+                name.map(|name| name.to_string()).unwrap_or_else(|| format!("{}", other)),
+                layer_names(layers),
+            ))
+            .unwrap_or_else(|_| (format!("{}", other), "-".to_owned()));
+
+        // Contacts only exist while touching, so ended collisions have none
+        let (contacts, impulse) = match collisions.get(entity1, entity2) {
+            Some(contacts) if started => (
+                world_contacts(contacts, target, &q_collider_parents, &q_positions),
+                contacts.total_normal_impulse,
+            ),
+            _ => (Vec::new(), 0.0),
+        };
+        collision_inspector.push(CollisionRecord {
+            started,
+            time: time.elapsed_seconds(),
+            other_name,
+            other_layers,
+            contacts,
+            impulse,
+        });
+    }
+}
+
+pub fn collision_debug_panel(
+    collision_inspector: Res<CollisionInspector>,
+    editor: Res<Editor>,
+    q_player_body: Query<Entity, With<PlayerBody>>,
+    q_names: Query<&Name>,
+) -> String {
+    let Some(target) = resolve_target(collision_inspector.target, &editor, &q_player_body) else {
+        return "Inspecting: nothing".to_owned();
+    };
+    let target_name = q_names.get(target)
+        .map(|name| name.to_string())
+        .unwrap_or_else(|_| format!("{}", target));
+    let mut collision_string = format!("Inspecting: {}", target_name);
+    for record in collision_inspector.records.iter().rev() {
+        let state = if record.started { "start" } else { "end" };
+        collision_string += &format!("\n{:>8.2}s {:<5} {} [{}] impulse {:.2}",
+            record.time,
+            state,
+            record.other_name,
+            record.other_layers,
+            record.impulse);
+        for (point, normal) in &record.contacts {
+            collision_string += &format!("\n    at {:.2} n {:.2}", point, normal);
+        }
+    }
+    collision_string
+}
+
+pub fn draw_collision_gizmos(
+    mut gizmos: Gizmos,
+    collision_inspector: Res<CollisionInspector>,
+    editor: Res<Editor>,
+    collisions: Res<Collisions>,
+    q_player_body: Query<Entity, With<PlayerBody>>,
+    q_collider_parents: Query<&ColliderParent>,
+    q_positions: Query<(&Position, &Rotation)>,
+) {
+    let Some(target) = resolve_target(collision_inspector.target, &editor, &q_player_body) else {
+        return;
+    };
+    // Live contacts of the target
+    for contacts in collisions.iter() {
+        if !contacts.during_current_frame {
+            continue;
+        }
+        if !is_target(contacts.entity1, target, &q_collider_parents) && !is_target(contacts.entity2, target, &q_collider_parents) {
+            continue;
+        }
+        for (point, normal) in world_contacts(contacts, target, &q_collider_parents, &q_positions) {
+            gizmos.sphere(point, Quat::IDENTITY, CONTACT_GIZMO_RADIUS, RED);
+            gizmos.arrow(point, point + normal * CONTACT_NORMAL_GIZMO_LENGTH, YELLOW);
+        }
+    }
+    // Where the most recent recorded collisions started
+    for record in collision_inspector.records.iter().filter(|record| record.started) {
+        for (point, _normal) in &record.contacts {
+            gizmos.sphere(*point, Quat::IDENTITY, CONTACT_GIZMO_RADIUS * 2.0, ORANGE);
+        }
+    }
+}
+
+pub fn inspect_command(
+    In(args): In<Vec<String>>,
+    mut collision_inspector: ResMut<CollisionInspector>,
+    q_names: Query<(Entity, &Name)>,
+) -> ConsoleCommandResult {
+    // Level object names may contain spaces
+    let name = args.join(" ");
+    let target = match name.as_str() {
+        "" | "player" => CollisionInspectorTarget::Player,
+        "selected" => CollisionInspectorTarget::Selected,
+        "off" => CollisionInspectorTarget::Off,
+        _ => {
+            let (entity, _name) = q_names.iter()
+                .find(|(_entity, entity_name)| entity_name.as_str().eq_ignore_ascii_case(&name))
+                .ok_or(format!("no entity named '{}'", name))?;
+            CollisionInspectorTarget::Entity(entity)
+        },
+    };
+    if target != collision_inspector.target {
+        collision_inspector.records.clear();
+    }
+    collision_inspector.target = target;
+    Ok(format!("Inspecting {}", if name.is_empty() { "player" } else { &name }))
+}
@@ -0,0 +1,289 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use bevy::{ecs::system::SystemId, input::{keyboard::{Key, KeyboardInput}, ButtonState}, prelude::*};
+
+use crate::{controls::controls::InputMap, states::loading::GameAssets, Game};
+
+const CONSOLE_LOG_LINES: usize = 12;
+/// Printed lines kept for the console, older ones are dropped.
+const CONSOLE_LOG_LENGTH: usize = 200;
+const CONSOLE_HISTORY_LENGTH: usize = 50;
+const CONSOLE_BACKGROUND_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.8);
+
+pub type ConsoleCommandResult = Result<String, String>;
+
+pub struct ConsoleCommand {
+    pub help: &'static str,
+    pub completions: Vec<String>,
+    pub system_id: SystemId<Vec<String>, ConsoleCommandResult>,
+}
+
+/// Every command that can be typed into the console, by name.
+#[derive(Resource, Default)]
+pub struct ConsoleCommands(pub BTreeMap<&'static str, ConsoleCommand>);
+
+#[derive(Resource, Default)]
+pub struct Console {
+    pub open: bool,
+    pub input: String,
+    pub log: VecDeque<String>,
+    pub history: Vec<String>,
+    history_index: Option<usize>,
+}
+
+#[derive(Component)]
+pub struct ConsoleDisplay;
+
+pub trait ConsoleCommandAppExt {
+    /// Registers `system` to run with the whitespace separated arguments when `name` is entered.
+    fn add_console_command<M>(&mut self,
+        name: &'static str,
+        help: &'static str,
+        system: impl IntoSystem<Vec<String>, ConsoleCommandResult, M> + 'static) -> &mut Self;
+    /// Adds values offered by tab completion for the first argument of `name`.
+    fn add_console_completions(&mut self, name: &'static str, completions: &[&str]) -> &mut Self;
+}
+
+impl ConsoleCommandAppExt for App {
+    fn add_console_command<M>(&mut self,
+            name: &'static str,
+            help: &'static str,
+            system: impl IntoSystem<Vec<String>, ConsoleCommandResult, M> + 'static) -> &mut Self {
+        let system_id = self.world_mut().register_system(system);
+        self.world_mut()
+            .get_resource_or_insert_with(ConsoleCommands::default)
+            .0.insert(name, ConsoleCommand {
+                help,
+                completions: Vec::new(),
+                system_id,
+            });
+        self
+    }
+    fn add_console_completions(&mut self, name: &'static str, completions: &[&str]) -> &mut Self {
+        if let Some(console_command) = self.world_mut()
+                .get_resource_or_insert_with(ConsoleCommands::default)
+                .0.get_mut(name) {
+            console_command.completions.extend(completions.iter().map(|completion| completion.to_string()));
+        }
+        self
+    }
+}
+
+impl Console {
+    pub fn print(&mut self, line: impl Into<String>) {
+        self.log.push_back(line.into());
+        if self.log.len() > CONSOLE_LOG_LENGTH {
+            self.log.pop_front();
+        }
+    }
+    fn push_history(&mut self, line: String) {
+        if self.history.last() != Some(&line) {
+            self.history.push(line);
+        }
+        if self.history.len() > CONSOLE_HISTORY_LENGTH {
+            self.history.remove(0);
+        }
+        self.history_index = None;
+    }
+    fn previous_history(&mut self) {
+        if self.history.is_empty() {
+            return;
+        }
+        let history_index = match self.history_index {
+            Some(history_index) => history_index.saturating_sub(1),
+            None => self.history.len() - 1,
+        };
+        self.history_index = Some(history_index);
+        self.input = self.history[history_index].to_owned();
+    }
+    fn next_history(&mut self) {
+        let Some(history_index) = self.history_index else {
+            return;
+        };
+        if history_index + 1 < self.history.len() {
+            self.history_index = Some(history_index + 1);
+            self.input = self.history[history_index + 1].to_owned();
+        } else {
+            self.history_index = None;
+            self.input.clear();
+        }
+    }
+    fn autocomplete(&mut self, console_commands: &ConsoleCommands) {
+        let mut words: Vec<&str> = self.input.split(' ').collect();
+        let candidates: Vec<String> = if words.len() <= 1 {
+            console_commands.0.keys().map(|name| name.to_string()).collect()
+        } else if words.len() == 2 {
+            console_commands.0.get(words[0])
+                .map(|console_command| console_command.completions.to_owned())
+                .unwrap_or_default()
+        } else {
+            return;
+        };
+        let partial = words.last().copied().unwrap_or_default();
+        let matches: Vec<&String> = candidates.iter()
+            .filter(|candidate| candidate.starts_with(partial))
+            .collect();
+        match matches.len() {
+            0 => {},
+            1 => {
+                let completed = matches[0].to_owned();
+                words.pop();
+                words.push(&completed);
+                self.input = words.join(" ") + " ";
+            },
+            _ => {
+                let options = matches.iter().map(|option| option.as_str()).collect::<Vec<&str>>().join("  ");
+                self.print(options);
+            },
+        }
+    }
+}
+
+pub fn console_open(console: Res<Console>) -> bool {
+    console.open
+}
+
+pub fn setup_console(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+) {
+    let text_style = TextStyle {
+        font: game_assets.debug_font.clone(),
+        font_size: 16.0,
+        ..default()
+    };
+    commands.spawn((
+        ConsoleDisplay,
+        TextBundle {
+            visibility: Visibility::Hidden,
+            text: Text::from_sections([
+                TextSection::new("", text_style.clone()),
+                TextSection::new("", text_style.clone()),
+            ]),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(0.0),
+                left: Val::Px(0.0),
+                width: Val::Percent(100.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            },
+            background_color: CONSOLE_BACKGROUND_COLOR.into(),
+            z_index: ZIndex::Global(10),
+            ..default()
+        },
+    ));
+}
+
+pub fn handle_console_input(
+    mut commands: Commands,
+    mut ev_keyboard_input: EventReader<KeyboardInput>,
+    key: Res<ButtonInput<KeyCode>>,
+    game: Res<Game>,
+    mut console: ResMut<Console>,
+    console_commands: Res<ConsoleCommands>,
+) {
+    let input_map = InputMap::default();
+    if game.dev_mode && key.just_pressed(input_map.console) {
+        console.open = !console.open;
+        ev_keyboard_input.clear();
+        return;
+    }
+    if !console.open {
+        ev_keyboard_input.clear();
+        return;
+    }
+    for ev in ev_keyboard_input.read() {
+        if ev.state != ButtonState::Pressed || ev.key_code == input_map.console {
+            continue;
+        }
+        match &ev.logical_key {
+            Key::Character(character) => console.input.push_str(character),
+            Key::Space => console.input.push(' '),
+            Key::Backspace => {
+                console.input.pop();
+            },
+            Key::ArrowUp => console.previous_history(),
+            Key::ArrowDown => console.next_history(),
+            Key::Tab => console.autocomplete(&console_commands),
+            Key::Escape => console.open = false,
+            Key::Enter => {
+                let line = console.input.trim().to_owned();
+                console.input.clear();
+                if line.is_empty() {
+                    continue;
+                }
+                console.print(format!("> {}", line));
+                console.push_history(line.to_owned());
+                run_console_line(commands.reborrow(), &mut console, &console_commands, &line);
+            },
+            _ => {},
+        }
+    }
+}
+
+fn run_console_line(
+    mut commands: Commands,
+    console: &mut Console,
+    console_commands: &ConsoleCommands,
+    line: &str,
+) {
+    let mut words = line.split_whitespace().map(|word| word.to_owned());
+    let Some(name) = words.next() else {
+        return;
+    };
+    let args: Vec<String> = words.collect();
+
+    match name.as_str() {
+        "help" => {
+            for (name, console_command) in console_commands.0.iter() {
+                console.print(format!("{} - {}", name, console_command.help));
+            }
+        },
+        "clear" => console.log.clear(),
+        _ => match console_commands.0.get(name.as_str()) {
+            Some(console_command) => {
+                // Command systems need world access, so run them once this system is done
+                let system_id = console_command.system_id;
+                commands.add(move |world: &mut World| {
+                    let output = match world.run_system_with_input(system_id, args) {
+                        Ok(Ok(output)) => output,
+                        Ok(Err(error)) => format!("error: {}", error),
+                        Err(error) => format!("error: {:?}", error),
+                    };
+                    if !output.is_empty() {
+                        world.resource_mut::<Console>().print(output);
+                    }
+                });
+            },
+            None => console.print(format!("Unknown command '{}', try 'help'", name)),
+        },
+    }
+}
+
+pub fn update_console_display(
+    console: Res<Console>,
+    mut q_console_display: Query<(&mut Text, &mut Visibility), With<ConsoleDisplay>>,
+) {
+    if !console.is_changed() {
+        return;
+    }
+    let Ok((mut text, mut visibility)) = q_console_display.get_single_mut() else {
+        return;
+    };
+    *visibility = if console.open {
+        Visibility::Visible
+    } else {
+        Visibility::Hidden
+    };
+    let log_start = console.log.len().saturating_sub(CONSOLE_LOG_LINES);
+    let mut log = console.log.range(log_start..)
+        .map(|line| line.as_str())
+        .collect::<Vec<&str>>()
+        .join("\n");
+    if !log.is_empty() {
+        log.push('\n');
+    }
+    text.sections[0].value = log;
+    text.sections[1].value = format!("> {}_", console.input);
+}
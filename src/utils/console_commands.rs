@@ -0,0 +1,127 @@
+use avian3d::prelude::Gravity;
+use bevy::prelude::*;
+
+use crate::{controls::player::PlayerTuning, editor::editor::editor_palette, entities::{player::player::{Player, PlayerBailEvent}, EntityCollisionLayers}, levels::level::{LevelBody, LevelObject, LevelShape}};
+
+use super::console::ConsoleCommandResult;
+
+const SPAWN_DISTANCE: f32 = 3.0;
+const SPAWN_STACK_HEIGHT: f32 = 1.1;
+const MAX_SPAWN_COUNT: usize = 100;
+
+fn parse_arg<T: std::str::FromStr>(args: &[String], index: usize, name: &str) -> Result<T, String> {
+    let arg = args.get(index).ok_or(format!("missing {}", name))?;
+    arg.parse::<T>().map_err(|_| format!("invalid {} '{}'", name, arg))
+}
+
+pub fn tp_command(
+    In(args): In<Vec<String>>,
+    mut q_player: Query<&mut Player>,
+) -> ConsoleCommandResult {
+    let location = Vec3::new(
+        parse_arg(&args, 0, "x")?,
+        parse_arg(&args, 1, "y")?,
+        parse_arg(&args, 2, "z")?,
+    );
+    let mut player = q_player.get_single_mut().map_err(|_| "no player")?;
+    player.set_location(location);
+    player.set_velocity(Vec3::ZERO);
+    Ok(format!("Teleported to {}", location))
+}
+
+pub fn bail_command(
+    In(_args): In<Vec<String>>,
+    mut ev_player_bail: EventWriter<PlayerBailEvent>,
+    q_player: Query<(Entity, &Player)>,
+) -> ConsoleCommandResult {
+    let (player_entity, player) = q_player.get_single().map_err(|_| "no player")?;
+    ev_player_bail.send(PlayerBailEvent((player_entity, !player.bailed)));
+    Ok(String::new())
+}
+
+pub fn spawn_command(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    q_player: Query<&Player>,
+) -> ConsoleCommandResult {
+    let name: String = parse_arg(&args, 0, "object")?;
+    let count = if args.len() > 1 {
+        parse_arg::<usize>(&args, 1, "count")?.min(MAX_SPAWN_COUNT)
+    } else {
+        1
+    };
+    let level_object = editor_palette().into_iter()
+        .find(|level_object| level_object.name.as_deref()
+            .is_some_and(|object_name| object_name.eq_ignore_ascii_case(&name)))
+        .ok_or(format!("unknown object '{}'", name))?;
+    let player = q_player.get_single().map_err(|_| "no player")?;
+
+    // Console spawned primitives are props that can be knocked around, ramps and models stay put
+    let level_object = match level_object.shape {
+        LevelShape::Gltf { .. }
+            | LevelShape::Ramp { .. }
+            | LevelShape::QuarterPipe { .. }
+            | LevelShape::HalfPipe { .. } => level_object,
+        _ => LevelObject {
+            body: LevelBody::Dynamic,
+            layer: EntityCollisionLayers::Props,
+            ..level_object
+        },
+    };
+
+    // Stack the objects in front of the player
+    let forward = Quat::from_rotation_y(player.get_rotation().y).mul_vec3(Vec3::NEG_Z);
+    let base_location = player.get_location() + forward * SPAWN_DISTANCE;
+    for i in 0..count {
+        LevelObject {
+            location: base_location + Vec3::Y * (0.5 + i as f32 * SPAWN_STACK_HEIGHT),
+            ..level_object.clone()
+        }.spawn(commands.reborrow(),
+            &asset_server,
+            meshes.reborrow(),
+            materials.reborrow());
+    }
+    Ok(format!("Spawned {} {}", count, name))
+}
+
+pub fn gravity_command(
+    In(args): In<Vec<String>>,
+    mut gravity: ResMut<Gravity>,
+    mut player_tuning: ResMut<PlayerTuning>,
+) -> ConsoleCommandResult {
+    let value: f32 = parse_arg(&args, 0, "gravity")?;
+    gravity.0 = Vec3::new(0.0, value, 0.0);
+    player_tuning.gravity = value;
+    Ok(format!("Gravity set to {}", value))
+}
+
+pub fn timescale_command(
+    In(args): In<Vec<String>>,
+    mut time: ResMut<Time<Virtual>>,
+) -> ConsoleCommandResult {
+    let value: f32 = parse_arg(&args, 0, "scale")?;
+    if !value.is_finite() || value < 0.0 {
+        return Err("scale must be a finite number that is not negative".to_owned());
+    }
+    // Physics steps by virtual time, so this slows both in lock-step
+    time.set_relative_speed(value);
+    Ok(format!("Time scale set to {}", value))
+}
+
+pub fn set_command(
+    In(args): In<Vec<String>>,
+    mut player_tuning: ResMut<PlayerTuning>,
+) -> ConsoleCommandResult {
+    let name: String = parse_arg(&args, 0, "parameter")?;
+    let Some(parameter) = player_tuning.get_mut(&name) else {
+        return Err(format!("unknown parameter '{}', one of: {}", name, PlayerTuning::NAMES.join(", ")));
+    };
+    if args.len() < 2 {
+        return Ok(format!("{} = {}", name, parameter));
+    }
+    *parameter = parse_arg(&args, 1, "value")?;
+    Ok(format!("{} set to {}", name, parameter))
+}
@@ -1,32 +1,103 @@
 use avian3d::prelude::{Collider, CollidingEntities};
 use bevy::prelude::*;
 
-use crate::{entities::player::player::{Player, PlayerBody}, Game};
+use crate::{entities::player::player::{Player, PlayerBody}, states::loading::GameAssets, Game};
+
+use super::ui::set_section_text;
+
+/// Hotkeys handed out to debug panels in registration order.
+const DEBUG_PANEL_HOTKEYS: [KeyCode; 8] = [
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F12,
+];
+
+const DEBUG_PANEL_HEADER_SECTION: usize = 0;
+const DEBUG_PANEL_BODY_SECTION: usize = 1;
 
 #[derive(Component, Default)]
-pub struct DebugDisplay {
-    pub visibility: Visibility
-}
+pub struct DebugDisplay;
 
+/// Text entity showing the panel registered at `index` in [`DebugPanels`].
 #[derive(Component)]
-pub struct PlayerDebugDisplay;
-#[derive(Component)]
-pub struct KeyPressDebugDisplay;
-#[derive(Component)]
-pub struct ColliderDebugDisplay;
+pub struct DebugPanel {
+    pub index: usize,
+}
+
+pub struct DebugPanelInfo {
+    pub label: &'static str,
+    pub hotkey: Option<KeyCode>,
+    pub collapsed: bool,
+}
+
+#[derive(Resource, Default)]
+pub struct DebugPanels(pub Vec<DebugPanelInfo>);
+
+impl DebugPanelInfo {
+    pub fn header(&self) -> String {
+        let hotkey = self.hotkey
+            .map(|hotkey| format!("[{:?}] ", hotkey))
+            .unwrap_or_default();
+        let state = if self.collapsed { "+" } else { "-" };
+        format!("{}{} {}\n", hotkey, self.label, state)
+    }
+}
+
+pub trait DebugPanelAppExt {
+    /// Adds a labeled section to the debug overlay filled with the text returned by `system`.
+    fn add_debug_panel<M>(&mut self, label: &'static str, system: impl IntoSystem<(), String, M>) -> &mut Self;
+}
+
+impl DebugPanelAppExt for App {
+    fn add_debug_panel<M>(&mut self, label: &'static str, system: impl IntoSystem<(), String, M>) -> &mut Self {
+        let index = {
+            let mut debug_panels = self.world_mut().get_resource_or_insert_with(DebugPanels::default);
+            let index = debug_panels.0.len();
+            debug_panels.0.push(DebugPanelInfo {
+                label,
+                hotkey: DEBUG_PANEL_HOTKEYS.get(index).copied(),
+                collapsed: false,
+            });
+            index
+        };
+        let write_debug_panel = move |In(value): In<String>, mut q_debug_panels: Query<(&DebugPanel, &mut Text)>| {
+            for (debug_panel, mut text) in q_debug_panels.iter_mut() {
+                if debug_panel.index == index {
+                    set_section_text(&mut text, DEBUG_PANEL_BODY_SECTION, value.to_owned());
+                }
+            }
+        };
+        // Panels only do work while they can actually be seen
+        let debug_panel_visible = move |debug_panels: Res<DebugPanels>, q_debug_display: Query<&Visibility, With<DebugDisplay>>| {
+            !debug_panels.0[index].collapsed
+                && q_debug_display.iter().any(|visibility| *visibility != Visibility::Hidden)
+        };
+        self.add_systems(Update, system.pipe(write_debug_panel).run_if(debug_panel_visible))
+    }
+}
 
 pub fn setup_debug_screen(
     mut commands: Commands,
-    game: Res<Game>
+    game: Res<Game>,
+    game_assets: Res<GameAssets>,
+    debug_panels: Res<DebugPanels>,
 ) {
     let mut visibility = Visibility::Hidden;
     if game.dev_mode {
         visibility = Visibility::Visible;
     }
+    let text_style = TextStyle {
+        font: game_assets.debug_font.clone(),
+        font_size: 16.0,
+        ..default()
+    };
     commands.spawn((
-        DebugDisplay {
-            visibility,
-        },
+        DebugDisplay,
         NodeBundle {
             visibility,
             style: Style {
@@ -38,68 +109,76 @@ pub fn setup_debug_screen(
             ..default()
         }
     )).with_children(|parent| {
-        parent.spawn(PlayerDebugDisplay);
-        parent.spawn(KeyPressDebugDisplay);
-        parent.spawn(ColliderDebugDisplay);
+        // Text is spawned once here and only its section values are updated afterwards
+        for (index, debug_panel) in debug_panels.0.iter().enumerate() {
+            parent.spawn((
+                DebugPanel {
+                    index,
+                },
+                TextBundle::from_sections([
+                    TextSection::new(debug_panel.header(), text_style.clone()),
+                    TextSection::new("", text_style.clone()),
+                ]),
+            ));
+        }
     });
 }
 
-pub fn update_debug_screen(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
+pub fn handle_debug_panel_keys(
     key: Res<ButtonInput<KeyCode>>,
-    mut player: Query<&Player>,
-    mut q_player_debug_display: Query<Entity, With<PlayerDebugDisplay>>,
-    mut q_key_press_debug_display: Query<Entity, With<KeyPressDebugDisplay>>,
-    mut q_collider_debug_display: Query<Entity, With<ColliderDebugDisplay>>,
-    q_colliding_entities: Query<&CollidingEntities, (
-        With<Collider>,
-        With<PlayerBody>
-    )>
+    mut debug_panels: ResMut<DebugPanels>,
+    mut q_debug_panels: Query<(&DebugPanel, &mut Text)>,
 ) {
-    let player = player.single_mut();
-    let player_debug_display = q_player_debug_display.single_mut();
-    let key_press_debug_display = q_key_press_debug_display.single_mut();
-    let collider_debug_display = q_collider_debug_display.single_mut();
+    for (debug_panel, mut text) in q_debug_panels.iter_mut() {
+        let debug_panel_info = &mut debug_panels.0[debug_panel.index];
+        let Some(hotkey) = debug_panel_info.hotkey else {
+            continue;
+        };
+        if !key.just_pressed(hotkey) {
+            continue;
+        }
+        debug_panel_info.collapsed = !debug_panel_info.collapsed;
+        set_section_text(&mut text, DEBUG_PANEL_HEADER_SECTION, debug_panel_info.header());
+        if debug_panel_info.collapsed {
+            set_section_text(&mut text, DEBUG_PANEL_BODY_SECTION, String::new());
+        }
+    }
+}
 
-    let text_style = TextStyle {
-        font: asset_server.load("fonts/Roboto/Roboto-Light.ttf"),
-        font_size: 16.0,
-        ..default()
+pub fn player_debug_panel(
+    player: Query<&Player>,
+) -> String {
+    let Ok(player) = player.get_single() else {
+        return String::new();
     };
+    format!("{}", player)
+}
 
-    // Create location display
-    commands.entity(player_debug_display).insert(TextBundle::from_section(
-        format!("{}", player),
-        text_style.to_owned()
-    ));
-
-    // Grab pressed keys and build string
-    let mut keys = String::new();
-    key.get_pressed().for_each(|key | {
-        keys += &format!("{:?} ", key);
-    });
-    keys = keys.trim().to_owned();
-
-    // Create key display
-    commands.entity(key_press_debug_display).insert(TextBundle::from_section(
-        format!("Keys: {}", keys),
-        text_style.to_owned()
-    ));
+pub fn key_press_debug_panel(
+    key: Res<ButtonInput<KeyCode>>,
+) -> String {
+    // Grab pressed keys and build string, sorted so the order is stable between frames
+    let mut keys: Vec<String> = key.get_pressed()
+        .map(|key| format!("{:?}", key))
+        .collect();
+    keys.sort();
+    format!("Keys: {}", keys.join(" "))
+}
 
+pub fn collider_debug_panel(
+    q_colliding_entities: Query<&CollidingEntities, (
+        With<Collider>,
+        With<PlayerBody>
+    )>
+) -> String {
     let mut colliders_string = String::new();
     for colliding_entities in &q_colliding_entities {
+        let mut colliding_entities: Vec<&Entity> = colliding_entities.iter().collect();
+        colliding_entities.sort();
         colliders_string += &format!(
             "Player is colliding with: {:?}\n",
             colliding_entities
         );
     }
-
-    let colliders_string = colliders_string.trim();
-
-    // Create collider display
-    commands.entity(collider_debug_display).insert(TextBundle::from_section(
-        colliders_string,
-        text_style.to_owned()
-    ));
-}
\ No newline at end of file
+    colliders_string.trim().to_owned()
+}
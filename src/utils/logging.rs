@@ -0,0 +1,179 @@
+use std::{collections::VecDeque, fmt::Debug, fs::{File, OpenOptions}, io::Write, sync::{Arc, Mutex}, time::Instant};
+
+use bevy::{log::{tracing_subscriber::{layer::Context, Layer}, BoxedLayer}, prelude::*, utils::tracing::{field::{Field, Visit}, Event, Level, Subscriber}};
+
+use super::console::ConsoleCommandResult;
+
+/// Environment variable naming a file every log event is also appended to.
+pub const LOG_FILE_ENV: &str = "MELTING_POT_LOG_FILE";
+pub const LOG_FILTER: &str = "wgpu=error,naga=warn,MeltingPot=debug";
+pub const LOG_LEVEL_NAMES: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+
+const LOG_BUFFER_LENGTH: usize = 500;
+const LOG_PANEL_LINES: usize = 10;
+const LOG_CRATE_PREFIX: &str = "MeltingPot::";
+
+pub struct LogEntry {
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+/// Log events captured from `tracing`, shared between the subscriber layer and the app.
+pub struct LogCapture {
+    pub entries: VecDeque<LogEntry>,
+    pub file: Option<File>,
+    started: Instant,
+}
+
+#[derive(Resource, Clone)]
+pub struct LogBuffer(pub Arc<Mutex<LogCapture>>);
+
+/// Which captured events are shown in the log debug panel.
+#[derive(Resource)]
+pub struct LogPanelFilter {
+    pub level: Level,
+    pub target: String,
+}
+
+struct LogCaptureLayer(Arc<Mutex<LogCapture>>);
+
+#[derive(Default)]
+struct LogMessageVisitor {
+    message: String,
+    fields: Vec<String>,
+}
+
+impl Default for LogPanelFilter {
+    fn default() -> Self {
+        Self {
+            level: Level::INFO,
+            target: String::new(),
+        }
+    }
+}
+
+impl LogEntry {
+    /// Target with the crate name dropped, so our own modules read as `entities::player::player`.
+    pub fn short_target(&self) -> &str {
+        self.target.strip_prefix(LOG_CRATE_PREFIX).unwrap_or(&self.target)
+    }
+}
+
+impl Visit for LogMessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.fields.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for LogCaptureLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = LogMessageVisitor::default();
+        event.record(&mut visitor);
+        let mut message = visitor.message;
+        if !visitor.fields.is_empty() {
+            message = format!("{} {}", message, visitor.fields.join(" "));
+        }
+        let entry = LogEntry {
+            level: *event.metadata().level(),
+            target: event.metadata().target().to_owned(),
+            message,
+        };
+
+        let Ok(mut log_capture) = self.0.lock() else {
+            return;
+        };
+        let elapsed = log_capture.started.elapsed().as_secs_f32();
+        if let Some(file) = log_capture.file.as_mut() {
+            // Logging must never take the game down, so write errors are dropped
+            let _ = writeln!(file, "[{:>10.3}] {:>5} {}: {}", elapsed, entry.level, entry.target, entry.message);
+        }
+        log_capture.entries.push_back(entry);
+        if log_capture.entries.len() > LOG_BUFFER_LENGTH {
+            log_capture.entries.pop_front();
+        }
+    }
+}
+
+/// Opens a log file for appending, creating it if needed.
+fn open_log_file(path: &str) -> std::io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Custom layer for [`bevy::log::LogPlugin`] feeding the in-game log panel and the optional log file.
+pub fn log_capture_layer(app: &mut App) -> Option<BoxedLayer> {
+    let file = std::env::var(LOG_FILE_ENV).ok()
+        .and_then(|path| open_log_file(&path).ok());
+    let log_capture = Arc::new(Mutex::new(LogCapture {
+        entries: VecDeque::new(),
+        file,
+        started: Instant::now(),
+    }));
+    app.insert_resource(LogBuffer(log_capture.clone()));
+    Some(Box::new(LogCaptureLayer(log_capture)))
+}
+
+fn parse_level(name: &str) -> Option<Level> {
+    match name.to_lowercase().as_str() {
+        "error" => Some(Level::ERROR),
+        "warn" => Some(Level::WARN),
+        "info" => Some(Level::INFO),
+        "debug" => Some(Level::DEBUG),
+        "trace" => Some(Level::TRACE),
+        _ => None,
+    }
+}
+
+pub fn log_debug_panel(
+    log_buffer: Option<Res<LogBuffer>>,
+    log_panel_filter: Res<LogPanelFilter>,
+) -> String {
+    let Some(log_buffer) = log_buffer else {
+        return String::new();
+    };
+    let Ok(log_capture) = log_buffer.0.lock() else {
+        return String::new();
+    };
+    // Levels compare by verbosity, so anything at or below the filter level is shown
+    let mut lines: Vec<String> = log_capture.entries.iter().rev()
+        .filter(|entry| entry.level <= log_panel_filter.level)
+        .filter(|entry| entry.short_target().starts_with(&log_panel_filter.target))
+        .take(LOG_PANEL_LINES)
+        .map(|entry| format!("{:>5} {}: {}", entry.level, entry.short_target(), entry.message))
+        .collect();
+    lines.reverse();
+    format!("Filter: {} {}\n{}", log_panel_filter.level, log_panel_filter.target, lines.join("\n"))
+}
+
+pub fn logfilter_command(
+    In(args): In<Vec<String>>,
+    mut log_panel_filter: ResMut<LogPanelFilter>,
+) -> ConsoleCommandResult {
+    let Some(level_name) = args.first() else {
+        return Ok(format!("Log filter: {} {}", log_panel_filter.level, log_panel_filter.target));
+    };
+    log_panel_filter.level = parse_level(level_name)
+        .ok_or(format!("unknown level '{}', one of: {}", level_name, LOG_LEVEL_NAMES.join(", ")))?;
+    log_panel_filter.target = args.get(1).cloned().unwrap_or_default();
+    Ok(format!("Log filter set to {} {}", log_panel_filter.level, log_panel_filter.target))
+}
+
+pub fn logfile_command(
+    In(args): In<Vec<String>>,
+    log_buffer: Option<Res<LogBuffer>>,
+) -> ConsoleCommandResult {
+    let log_buffer = log_buffer.ok_or("log capture is not enabled")?;
+    let path = args.first().ok_or("missing path, or 'off'")?;
+    let mut log_capture = log_buffer.0.lock().map_err(|_| "log capture is unavailable")?;
+    if path == "off" {
+        log_capture.file = None;
+        return Ok("Stopped logging to file".to_owned());
+    }
+    let file = open_log_file(path).map_err(|error| format!("could not open '{}': {}", path, error))?;
+    log_capture.file = Some(file);
+    Ok(format!("Logging to {}", path))
+}
@@ -0,0 +1,14 @@
+use avian3d::math::{PI, TAU};
+
+/// Wraps an angle or angle difference into -PI..PI, so turning across the 0/TAU seam
+/// is not mistaken for a full turn.
+pub fn wrap_angle(angle: f32) -> f32 {
+    let angle = angle % TAU;
+    if angle > PI {
+        angle - TAU
+    } else if angle < -PI {
+        angle + TAU
+    } else {
+        angle
+    }
+}
@@ -1 +1,10 @@
-pub mod debug;
\ No newline at end of file
+pub mod collision_inspector;
+pub mod console;
+pub mod console_commands;
+pub mod debug;
+pub mod logging;
+pub mod math;
+pub mod perf;
+pub mod player_gizmos;
+pub mod time_control;
+pub mod ui;
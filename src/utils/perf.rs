@@ -0,0 +1,68 @@
+use std::time::Instant;
+
+use avian3d::prelude::{Collider, Collisions, RigidBody};
+use bevy::{diagnostic::{DiagnosticPath, Diagnostics, DiagnosticsStore, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin}, prelude::*};
+
+pub const PHYSICS_STEP_TIME: DiagnosticPath = DiagnosticPath::const_new("physics/step_time");
+
+/// Characters used to draw the frame time graph, from shortest to tallest bar.
+const SPARKLINE_LEVELS: [char; 6] = ['_', '.', '-', '~', '*', '^'];
+const SPARKLINE_LENGTH: usize = 60;
+
+/// When the current physics step started, if one is in progress.
+#[derive(Resource, Default)]
+pub struct PhysicsStepTimer(pub Option<Instant>);
+
+pub fn start_physics_step_timer(mut physics_step_timer: ResMut<PhysicsStepTimer>) {
+    physics_step_timer.0 = Some(Instant::now());
+}
+
+pub fn stop_physics_step_timer(
+    mut diagnostics: Diagnostics,
+    mut physics_step_timer: ResMut<PhysicsStepTimer>,
+) {
+    if let Some(started) = physics_step_timer.0.take() {
+        diagnostics.add_measurement(&PHYSICS_STEP_TIME, || started.elapsed().as_secs_f64() * 1000.0);
+    }
+}
+
+fn sparkline(values: &[f64]) -> String {
+    let max = values.iter().cloned().fold(f64::EPSILON, f64::max);
+    values.iter()
+        .map(|value| {
+            let level = (value / max * (SPARKLINE_LEVELS.len() - 1) as f64).round() as usize;
+            SPARKLINE_LEVELS[level.min(SPARKLINE_LEVELS.len() - 1)]
+        })
+        .collect()
+}
+
+pub fn perf_debug_panel(
+    diagnostics: Res<DiagnosticsStore>,
+    collisions: Res<Collisions>,
+    q_rigid_bodies: Query<(), With<RigidBody>>,
+    q_colliders: Query<(), With<Collider>>,
+) -> String {
+    let smoothed = |path: &DiagnosticPath| diagnostics.get(path)
+        .and_then(|diagnostic| diagnostic.smoothed())
+        .unwrap_or_default();
+
+    let frame_times: Vec<f64> = diagnostics.get(&FrameTimeDiagnosticsPlugin::FRAME_TIME)
+        .map(|diagnostic| diagnostic.values().cloned().collect())
+        .unwrap_or_default();
+    let frame_times = &frame_times[frame_times.len().saturating_sub(SPARKLINE_LENGTH)..];
+
+    let contacts: usize = collisions.iter()
+        .filter(|contacts| contacts.during_current_frame)
+        .map(|contacts| contacts.manifolds.iter().map(|manifold| manifold.contacts.len()).sum::<usize>())
+        .sum();
+
+    format!("FPS: {:.0}\nFrame time: {:.2} ms\n{}\nPhysics step: {:.2} ms\nEntities: {:.0}\nRigid bodies: {}, Colliders: {}, Contacts: {}",
+        smoothed(&FrameTimeDiagnosticsPlugin::FPS),
+        smoothed(&FrameTimeDiagnosticsPlugin::FRAME_TIME),
+        sparkline(frame_times),
+        smoothed(&PHYSICS_STEP_TIME),
+        smoothed(&EntityCountDiagnosticsPlugin::ENTITY_COUNT),
+        q_rigid_bodies.iter().count(),
+        q_colliders.iter().count(),
+        contacts)
+}
\ No newline at end of file
@@ -0,0 +1,91 @@
+use avian3d::prelude::{RayCaster, RayHits};
+use bevy::{color::palettes::css::{AQUA, DARK_GRAY, FUCHSIA, LIME, ORANGE, RED, WHITE, YELLOW}, prelude::*};
+
+use crate::{controls::controls::InputMap, entities::player::player::{Player, PlayerBody, PlayerCameraRay, PlayerFloorRay, PlayerInteractRay, PlayerStepRay}, Game};
+
+/// How many frames ahead the velocity arrow reaches, since player velocity is a per frame offset.
+const VELOCITY_GIZMO_FRAMES: f32 = 10.0;
+const NORMAL_GIZMO_LENGTH: f32 = 1.0;
+const LEAN_GIZMO_LENGTH: f32 = 1.5;
+const HIT_GIZMO_RADIUS: f32 = 0.08;
+
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct PlayerGizmos;
+
+pub fn handle_player_gizmos_key(
+    key: Res<ButtonInput<KeyCode>>,
+    game: Res<Game>,
+    mut config_store: ResMut<GizmoConfigStore>,
+) {
+    let input_map = InputMap::default();
+    if game.dev_mode && key.just_pressed(input_map.debug_player_gizmos) {
+        let (config, _) = config_store.config_mut::<PlayerGizmos>();
+        config.enabled = !config.enabled;
+    }
+}
+
+/// Draws a ray up to its max time of impact, with its first hit marked in `hit_color`.
+fn draw_ray(gizmos: &mut Gizmos<PlayerGizmos>, ray_caster: &RayCaster, ray_hits: Option<&RayHits>, hit_color: Srgba) {
+    let origin = ray_caster.global_origin();
+    let direction = ray_caster.global_direction();
+    let first_hit = ray_hits.and_then(|ray_hits| ray_hits.iter_sorted().next());
+    match first_hit {
+        Some(hit) => {
+            let point = origin + *direction * hit.time_of_impact;
+            gizmos.line(origin, point, hit_color);
+            gizmos.line(point, origin + *direction * ray_caster.max_time_of_impact, DARK_GRAY);
+            gizmos.sphere(point, Quat::IDENTITY, HIT_GIZMO_RADIUS, hit_color);
+        },
+        None => {
+            gizmos.line(origin, origin + *direction * ray_caster.max_time_of_impact, DARK_GRAY);
+        },
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn draw_player_gizmos(
+    mut gizmos: Gizmos<PlayerGizmos>,
+    q_player: Query<&Player>,
+    q_player_body: Query<&GlobalTransform, With<PlayerBody>>,
+    q_player_camera_ray: Query<(&RayCaster, Option<&RayHits>), With<PlayerCameraRay>>,
+    q_player_floor_ray: Query<(&RayCaster, Option<&RayHits>), With<PlayerFloorRay>>,
+    q_player_interact_ray: Query<(&RayCaster, Option<&RayHits>), With<PlayerInteractRay>>,
+    q_player_step_ray: Query<(&RayCaster, Option<&RayHits>), With<PlayerStepRay>>,
+) {
+    let (Ok(player), Ok(player_body_global_transform)) = (q_player.get_single(), q_player_body.get_single()) else {
+        return;
+    };
+
+    // Camera hits mean the camera is being pulled in towards the player
+    for (ray_caster, ray_hits) in q_player_camera_ray.iter() {
+        draw_ray(&mut gizmos, ray_caster, ray_hits, ORANGE);
+    }
+    // Floor hits are green while grounded, yellow while still snapping down
+    let floor_color = if player.is_on_floor { LIME } else { YELLOW };
+    for (ray_caster, ray_hits) in q_player_floor_ray.iter() {
+        draw_ray(&mut gizmos, ray_caster, ray_hits, floor_color);
+
+        // Ground normal at the floor hit
+        if let Some(hit) = ray_hits.and_then(|ray_hits| ray_hits.iter_sorted().next()) {
+            let point = ray_caster.global_origin() + *ray_caster.global_direction() * hit.time_of_impact;
+            gizmos.arrow(point, point + hit.normal * NORMAL_GIZMO_LENGTH, AQUA);
+        }
+    }
+    for (ray_caster, ray_hits) in q_player_interact_ray.iter() {
+        draw_ray(&mut gizmos, ray_caster, ray_hits, AQUA);
+    }
+    for (ray_caster, ray_hits) in q_player_step_ray.iter() {
+        draw_ray(&mut gizmos, ray_caster, ray_hits, WHITE);
+    }
+
+    let body_position = player_body_global_transform.translation();
+    let velocity = player.get_velocity() * VELOCITY_GIZMO_FRAMES;
+    if velocity.length_squared() > f32::EPSILON {
+        let velocity_color = if player.bailed { RED } else { LIME };
+        gizmos.arrow(body_position, body_position + velocity, velocity_color);
+    }
+
+    // Lean is the angle between the body up axis and world up
+    gizmos.line(body_position, body_position + Vec3::Y * LEAN_GIZMO_LENGTH, DARK_GRAY);
+    gizmos.line(body_position, body_position + *player_body_global_transform.up() * LEAN_GIZMO_LENGTH, FUCHSIA);
+}
@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+
+use crate::{controls::controls::InputMap, Game};
+
+use super::console::ConsoleCommandResult;
+
+/// Scales the time control keys move between, from slowest to fastest.
+const TIME_SCALE_STEPS: [f32; 7] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.0, 4.0];
+const MAX_STEP_FRAMES: u32 = 600;
+
+/// Frames still to be stepped while virtual time is paused.
+#[derive(Resource, Default)]
+pub struct TimeControl {
+    pub step_frames: u32,
+}
+
+pub fn handle_time_control_keys(
+    key: Res<ButtonInput<KeyCode>>,
+    game: Res<Game>,
+    mut time_control: ResMut<TimeControl>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+) {
+    if !game.dev_mode {
+        return;
+    }
+    let input_map = InputMap::default();
+    if key.just_pressed(input_map.debug_time_pause) {
+        if virtual_time.is_paused() {
+            virtual_time.unpause();
+        } else {
+            virtual_time.pause();
+        }
+        time_control.step_frames = 0;
+    }
+    if key.just_pressed(input_map.debug_time_step) {
+        virtual_time.pause();
+        time_control.step_frames += 1;
+    }
+    let relative_speed = virtual_time.relative_speed();
+    if key.just_pressed(input_map.debug_time_slower) {
+        let slower = TIME_SCALE_STEPS.iter().rev()
+            .find(|scale| **scale < relative_speed)
+            .copied()
+            .unwrap_or(TIME_SCALE_STEPS[0]);
+        virtual_time.set_relative_speed(slower);
+    }
+    if key.just_pressed(input_map.debug_time_faster) {
+        let faster = TIME_SCALE_STEPS.iter()
+            .find(|scale| **scale > relative_speed)
+            .copied()
+            .unwrap_or(TIME_SCALE_STEPS[TIME_SCALE_STEPS.len() - 1]);
+        virtual_time.set_relative_speed(faster);
+    }
+}
+
+/// Advances paused virtual time by exactly one fixed timestep per requested frame.
+///
+/// Runs before the fixed main loop, so physics and our `Update` player systems both see the same step.
+pub fn apply_time_step(
+    mut time_control: ResMut<TimeControl>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut time: ResMut<Time>,
+    fixed_time: Res<Time<Fixed>>,
+) {
+    if time_control.step_frames == 0 {
+        return;
+    }
+    if !virtual_time.is_paused() {
+        time_control.step_frames = 0;
+        return;
+    }
+    virtual_time.advance_by(fixed_time.timestep());
+    *time = virtual_time.as_generic();
+    time_control.step_frames -= 1;
+}
+
+/// Whether game time moves this frame, which while paused is only when a frame is stepped.
+pub fn game_time_running(time: Res<Time>) -> bool {
+    time.delta_seconds() > 0.0
+}
+
+pub fn time_debug_panel(
+    virtual_time: Res<Time<Virtual>>,
+    time_control: Res<TimeControl>,
+) -> String {
+    let input_map = InputMap::default();
+    let state = if virtual_time.is_paused() {
+        format!("paused, [{:?}] step", input_map.debug_time_step)
+    } else {
+        "running".to_owned()
+    };
+    let mut time_string = format!("Time scale: {:.2}x ({})", virtual_time.relative_speed(), state);
+    if time_control.step_frames > 0 {
+        time_string += &format!("\nStepping: {} frames left", time_control.step_frames);
+    }
+    time_string
+}
+
+pub fn pause_command(
+    In(_args): In<Vec<String>>,
+    mut time_control: ResMut<TimeControl>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+) -> ConsoleCommandResult {
+    time_control.step_frames = 0;
+    if virtual_time.is_paused() {
+        virtual_time.unpause();
+        Ok("Time resumed".to_owned())
+    } else {
+        virtual_time.pause();
+        Ok("Time paused".to_owned())
+    }
+}
+
+pub fn step_command(
+    In(args): In<Vec<String>>,
+    mut time_control: ResMut<TimeControl>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+) -> ConsoleCommandResult {
+    let frames = match args.first() {
+        Some(arg) => arg.parse::<u32>().map_err(|_| format!("invalid frames '{}'", arg))?,
+        None => 1,
+    };
+    virtual_time.pause();
+    time_control.step_frames = (time_control.step_frames + frames).min(MAX_STEP_FRAMES);
+    Ok(format!("Stepping {} frames", time_control.step_frames))
+}
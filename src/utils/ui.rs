@@ -0,0 +1,8 @@
+use bevy::prelude::*;
+
+/// Replaces the text of a section, leaving it untouched (and unchanged for layout) if equal.
+pub fn set_section_text(text: &mut Mut<Text>, section: usize, value: String) {
+    if text.sections[section].value != value {
+        text.sections[section].value = value;
+    }
+}
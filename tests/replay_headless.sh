@@ -0,0 +1,21 @@
+#!/usr/bin/env sh
+# Replays every recording under tests/replays headless twice and fails unless both runs
+# finish and print the same final player state.
+set -eu
+
+cd "$(dirname "$0")/.."
+
+status=0
+for recording in tests/replays/*.input.ron; do
+    first=$(cargo run --quiet -- --replay "$recording" --headless)
+    second=$(cargo run --quiet -- --replay "$recording" --headless)
+    if [ "$first" != "$second" ]; then
+        echo "FAIL $recording: final state differs between runs"
+        echo "$first"
+        echo "$second"
+        status=1
+    else
+        echo "ok   $recording"
+    fi
+done
+exit $status